@@ -0,0 +1,183 @@
+use std::{cmp::Reverse, fs, path::Path};
+
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{models::DomainError, tokenizer::TokenType};
+
+pub const DEFAULT_MODE: &str = "default";
+
+// Lexer definition as stored in a `.toml` or `.json` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LexerConfig {
+    pub rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub regex: String,
+    // Name of the `TokenType` produced, not needed for `skip` rules.
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub skip: bool,
+    // Higher priorities win when two rules match the same length.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_mode")]
+    pub mode: String,
+}
+
+fn default_mode() -> String {
+    DEFAULT_MODE.to_owned()
+}
+
+impl LexerConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<LexerConfig, DomainError> {
+        let path = path.as_ref();
+        let from_str = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => LexerConfig::from_toml,
+            Some("json") => LexerConfig::from_json,
+            _ => {
+                return Err(DomainError::UnsupportedConfigFormat(
+                    path.display().to_string(),
+                ))
+            }
+        };
+        let content = fs::read_to_string(path)
+            .map_err(|e| DomainError::ConfigIo(format!("{}: {}", path.display(), e)))?;
+        from_str(&content)
+    }
+    pub fn from_toml(content: &str) -> Result<LexerConfig, DomainError> {
+        let config: LexerConfig =
+            toml::from_str(content).map_err(|e| DomainError::ConfigSyntax(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+    pub fn from_json(content: &str) -> Result<LexerConfig, DomainError> {
+        let config: LexerConfig =
+            serde_json::from_str(content).map_err(|e| DomainError::ConfigSyntax(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+    pub fn validate(&self) -> Result<(), DomainError> {
+        for rule in self.rules.iter() {
+            Regex::new(&rule.regex).map_err(|e| DomainError::InvalidRegex {
+                regex: rule.regex.clone(),
+                message: e.to_string(),
+            })?;
+            rule.token_type()?;
+        }
+        Ok(())
+    }
+    // Builds the `Tokenizer` spec for one lexer mode, ordered by priority and
+    // then by position in the file.
+    pub fn spec(&self, mode: &str) -> Result<IndexMap<String, TokenType>, DomainError> {
+        let mut rules: Vec<&RuleConfig> =
+            self.rules.iter().filter(|rule| rule.mode == mode).collect();
+        rules.sort_by_key(|rule| Reverse(rule.priority));
+        rules
+            .into_iter()
+            .map(|rule| Ok((rule.regex.clone(), rule.token_type()?)))
+            .collect()
+    }
+}
+
+impl RuleConfig {
+    fn token_type(&self) -> Result<TokenType, DomainError> {
+        if self.skip {
+            return Ok(TokenType::Null);
+        }
+        match self.kind.as_deref() {
+            Some(kind) => TokenType::from_name(kind)
+                .ok_or_else(|| DomainError::UnknownTokenKind(kind.to_owned())),
+            None => Err(DomainError::MissingTokenKind {
+                regex: self.regex.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_is_ordered_by_priority() {
+        let config = LexerConfig::from_json(
+            r#"{"rules": [
+                {"regex": "^\\d+", "kind": "Number"},
+                {"regex": "^[0-9]+", "kind": "String", "priority": 1},
+                {"regex": "^;", "kind": "Number", "mode": "other"}
+            ]}"#,
+        )
+        .unwrap();
+        let spec = config.spec(DEFAULT_MODE).unwrap();
+        assert_eq!(
+            spec.into_iter().collect::<Vec<_>>(),
+            vec![
+                (r"^[0-9]+".to_owned(), TokenType::String),
+                (r"^\d+".to_owned(), TokenType::Number),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let error = LexerConfig::from_toml(
+            r#"
+            [[rules]]
+            regex = '^(\d+'
+            kind = "Number"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            DomainError::InvalidRegex { regex, .. } if regex == r"^(\d+"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_and_missing_kinds() {
+        assert_eq!(
+            LexerConfig::from_json(r#"{"rules": [{"regex": "^a", "kind": "Letter"}]}"#),
+            Err(DomainError::UnknownTokenKind("Letter".to_owned()))
+        );
+        assert_eq!(
+            LexerConfig::from_json(r#"{"rules": [{"regex": "^a"}]}"#),
+            Err(DomainError::MissingTokenKind {
+                regex: "^a".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_bundled_config() {
+        let spec = LexerConfig::from_toml(include_str!("../lexer.toml"))
+            .unwrap()
+            .spec(DEFAULT_MODE)
+            .unwrap();
+        assert_eq!(
+            spec.into_iter().collect::<Vec<_>>(),
+            vec![
+                (r"^\s+".to_owned(), TokenType::Null),
+                (r"^\/\/.*".to_owned(), TokenType::Null),
+                (r"^\/\*[\s\S]*?\*\/".to_owned(), TokenType::Null),
+                (r"^\d+".to_owned(), TokenType::Number),
+                (r#"^"([^"]*)"|^'([^']*)'"#.to_owned(), TokenType::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert_eq!(
+            LexerConfig::load("lexer.yaml"),
+            Err(DomainError::UnsupportedConfigFormat(
+                "lexer.yaml".to_owned()
+            ))
+        );
+    }
+}
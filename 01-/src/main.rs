@@ -1,43 +1,102 @@
-#[macro_use]
-extern crate maplit;
+use std::{
+    env, fs,
+    io::{self, Read},
+    process,
+};
 
-use std::io;
+use indexmap::IndexMap;
 
 use tokenizer::TokenType;
 
-use crate::parser::Parser;
+use crate::{
+    config::{LexerConfig, DEFAULT_MODE},
+    models::DomainError,
+    parser::Parser,
+};
+mod config;
 mod models;
 mod parser;
 mod tokenizer;
+
+const USAGE: &str = "Usage: building_parser_from_scratch [--spec FILE] [FILE]...";
+
+// Parses each file given as argument, or stdin when there is none, and prints
+// the program as JSON. Tokens come from `--spec`, a `.toml` or `.json` lexer
+// config, or the bundled `lexer.toml`. Exits with 1 when any file fails and
+// with 2 on bad arguments or an invalid spec.
 fn main() {
-    let spec = hashmap![
-        r"^\s+".to_owned() => TokenType::Null,
-        // Comments
-        r"^\/\?.*/".to_owned() => TokenType::Null,
-        // Comments multi line
-        r"^\/\*[\s\S]*?\*\/".to_owned() => TokenType::Null,
-        r"^\d+".to_owned() => TokenType::Number,
-        r#""([^"]*)"|'([^']*)'"# .to_owned()=> TokenType::String,
-    ];
-    let mut parser = Parser::new(
-        spec,
-        r#"   
-        //comments
-        /**
-         * comments
-         */
-     "  hola    "  
-      "#
-        .to_string(),
-    );
-    let result = parser.parse();
-    match result {
+    let mut args = env::args().skip(1);
+    let mut spec_path = None;
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--spec" => match args.next() {
+                Some(path) => spec_path = Some(path),
+                None => {
+                    eprintln!("--spec expects a file\n{}", USAGE);
+                    process::exit(2);
+                }
+            },
+            _ => files.push(arg),
+        }
+    }
+    let spec = match spec(spec_path.as_deref()) {
+        Ok(spec) => spec,
+        Err(e) => {
+            eprintln!(
+                "{}: Error: {:?}",
+                spec_path.as_deref().unwrap_or("lexer.toml"),
+                e
+            );
+            process::exit(2);
+        }
+    };
+    let mut code = 0;
+    if files.is_empty() {
+        let mut source = String::new();
+        match io::stdin().read_to_string(&mut source) {
+            Ok(_) => code = parse("<stdin>", &spec, source),
+            Err(e) => {
+                eprintln!("<stdin>: {}", e);
+                code = 1;
+            }
+        }
+    }
+    for file in files.iter() {
+        match fs::read_to_string(file) {
+            Ok(source) => code = code.max(parse(file, &spec, source)),
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                code = 1;
+            }
+        }
+    }
+    process::exit(code);
+}
+
+fn parse(name: &str, spec: &IndexMap<String, TokenType>, source: String) -> i32 {
+    let mut parser = Parser::new(spec.clone(), source);
+    for warning in parser.warnings() {
+        eprintln!("{}: Warning: {:?}", name, warning);
+    }
+    match parser.parse() {
         Ok(parsed) => {
             serde_json::to_writer_pretty(io::stdout(), &parsed)
                 .expect("Failed to write JSON to stdout");
+            println!();
+            0
         }
         Err(e) => {
-            println!("Error: {:?}", e);
+            eprintln!("{}: Error: {:?}", name, e);
+            1
         }
     }
 }
+
+fn spec(path: Option<&str>) -> Result<IndexMap<String, TokenType>, DomainError> {
+    let config = match path {
+        Some(path) => LexerConfig::load(path)?,
+        None => LexerConfig::from_toml(include_str!("../lexer.toml"))?,
+    };
+    config.spec(DEFAULT_MODE)
+}
@@ -2,9 +2,19 @@ use std::num::ParseIntError;
 
 use serde::{Deserialize, Serialize};
 
+use crate::tokenizer::TokenType;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DomainError {
     ParseIntError,
+    UnexpectedEndOfInput,
+    UnexpectedToken(TokenType),
+    ConfigIo(String),
+    ConfigSyntax(String),
+    UnsupportedConfigFormat(String),
+    InvalidRegex { regex: String, message: String },
+    UnknownTokenKind(String),
+    MissingTokenKind { regex: String },
 }
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ParsedType {
@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 use crate::{
     models::{DomainError, Parsed, ParsedType, ParsedValue, Program},
-    tokenizer::{TokenType, TokenValue, Tokenizer},
+    tokenizer::{SpecWarning, TokenType, TokenValue, Tokenizer},
 };
 
 pub struct Parser {
@@ -10,26 +10,30 @@ pub struct Parser {
     look_ahead: Option<TokenValue>,
 }
 impl Parser {
-    pub fn new(spec: HashMap<String, TokenType>, to_parse: String) -> Parser {
+    pub fn new(spec: IndexMap<String, TokenType>, to_parse: String) -> Parser {
         Parser {
             tokenizer: Tokenizer::new(spec, to_parse.clone()),
             look_ahead: None,
         }
     }
+    pub fn warnings(&self) -> &[SpecWarning] {
+        self.tokenizer.warnings()
+    }
     pub fn parse(&mut self) -> Result<Program, DomainError> {
         self.look_ahead = self.tokenizer.get_next_token();
         self.program()
     }
 
     pub fn program(&mut self) -> Result<Program, DomainError> {
-        self.literal().map(|x| Program::new(x))
+        self.literal().map(Program::new)
     }
 
     pub fn literal(&mut self) -> Result<Parsed, DomainError> {
-        match self.look_ahead.as_ref().unwrap().token_type {
-            TokenType::Number => self.numeric_literal(),
-            TokenType::String => self.string_literal(),
-            TokenType::Null => todo!(),
+        match self.look_ahead.as_ref().map(|token| &token.token_type) {
+            Some(TokenType::Number) => self.numeric_literal(),
+            Some(TokenType::String) => self.string_literal(),
+            Some(token_type) => Err(DomainError::UnexpectedToken(token_type.clone())),
+            None => Err(DomainError::UnexpectedEndOfInput),
         }
     }
     // NumericLiteral
@@ -45,10 +49,10 @@ impl Parser {
     fn string_literal(&mut self) -> Result<Parsed, DomainError> {
         let token = self.eat(TokenType::String);
         let value = token.value.get_string();
-        return Ok(Parsed::new(
+        Ok(Parsed::new(
             ParsedType::StringLiteral,
             ParsedValue::String(value[1..value.len() - 1].to_string()),
-        ));
+        ))
     }
     fn eat(&mut self, token_type: TokenType) -> TokenValue {
         if self.look_ahead.is_none() {
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -10,24 +10,125 @@ pub enum TokenType {
     Number,
     String,
 }
+
+impl TokenType {
+    pub fn from_name(name: &str) -> Option<TokenType> {
+        match name {
+            "Null" => Some(TokenType::Null),
+            "Number" => Some(TokenType::Number),
+            "String" => Some(TokenType::String),
+            _ => None,
+        }
+    }
+}
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TokenValue {
     pub token_type: TokenType,
     pub value: ParsedValue,
 }
+
+// How `search_token` picks between rules that match at the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchPolicy {
+    // The longest match wins; rules earlier in the spec win ties.
+    #[default]
+    LongestMatch,
+    // The first rule in spec order that matches wins.
+    FirstMatch,
+}
+
+// Problems found in the spec when the tokenizer is built.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpecWarning {
+    // The regex does not start with `^`, it is matched at the cursor anyway.
+    MissingAnchor { pattern: String },
+    // The regex accepts the empty string, such matches are ignored.
+    MatchesEmpty { pattern: String },
+    // An earlier rule has the same regex once the anchor is ignored, or this
+    // rule is a literal that an earlier rule matches in full, so this one can
+    // never win.
+    Unreachable { pattern: String, shadowed_by: usize },
+}
+
+struct Rule {
+    pattern: String,
+    regex: Regex,
+    token_type: TokenType,
+}
+
 pub struct Tokenizer {
     to_parse: String,
     cursor: usize,
-    spec: HashMap<String, TokenType>,
+    rules: Vec<Rule>,
+    policy: MatchPolicy,
+    warnings: Vec<SpecWarning>,
 }
 
 impl Tokenizer {
-    pub fn new(spec: HashMap<String, TokenType>, to_parse: String) -> Tokenizer {
+    pub fn new(spec: IndexMap<String, TokenType>, to_parse: String) -> Tokenizer {
+        Tokenizer::with_policy(spec, to_parse, MatchPolicy::default())
+    }
+    pub fn with_policy(
+        spec: IndexMap<String, TokenType>,
+        to_parse: String,
+        policy: MatchPolicy,
+    ) -> Tokenizer {
+        let rules: Vec<Rule> = spec
+            .into_iter()
+            .map(|(pattern, token_type)| {
+                // Anchored so that a search stops at the cursor instead of
+                // scanning the rest of the input.
+                if let Err(e) = Regex::new(&pattern) {
+                    panic!("Invalid token regex {:?}: {}", pattern, e);
+                }
+                Rule {
+                    regex: Regex::new(&format!("^(?:{})", pattern)).unwrap(),
+                    pattern,
+                    token_type,
+                }
+            })
+            .collect();
+        let warnings = Tokenizer::validate(&rules);
         Tokenizer {
             to_parse,
             cursor: 0,
-            spec,
+            rules,
+            policy,
+            warnings,
+        }
+    }
+    pub fn warnings(&self) -> &[SpecWarning] {
+        &self.warnings
+    }
+    fn validate(rules: &[Rule]) -> Vec<SpecWarning> {
+        let mut warnings = vec![];
+        for (index, rule) in rules.iter().enumerate() {
+            if !rule.pattern.starts_with('^') {
+                warnings.push(SpecWarning::MissingAnchor {
+                    pattern: rule.pattern.clone(),
+                });
+            }
+            if rule.regex.is_match("") {
+                warnings.push(SpecWarning::MatchesEmpty {
+                    pattern: rule.pattern.clone(),
+                });
+            }
+            if let Some(shadowed_by) = rules[..index].iter().position(|earlier| {
+                unanchored(&earlier.pattern) == unanchored(&rule.pattern)
+                    || literal(&rule.pattern).is_some_and(|text| {
+                        earlier
+                            .regex
+                            .find(&text)
+                            .is_some_and(|matched| matched.end() == text.len())
+                    })
+            }) {
+                warnings.push(SpecWarning::Unreachable {
+                    pattern: rule.pattern.clone(),
+                    shadowed_by,
+                });
+            }
         }
+        warnings
     }
     fn has_more_tokens(&self) -> bool {
         self.cursor < self.to_parse.len()
@@ -50,18 +151,25 @@ impl Tokenizer {
         None
     }
     fn search_token(&self, string_data: &str) -> Option<(String, TokenType)> {
-        for (key, value) in self.spec.iter() {
-            if let Some(token) = self.match_token(key, &string_data) {
-                return Some((token, value.clone()));
+        let mut best: Option<(&str, &Rule)> = None;
+        for rule in self.rules.iter() {
+            if let Some(token) = self.match_token(&rule.regex, string_data) {
+                if self.policy == MatchPolicy::FirstMatch {
+                    return Some((token.to_string(), rule.token_type.clone()));
+                }
+                if best.is_none_or(|(longest, _)| token.len() > longest.len()) {
+                    best = Some((token, rule));
+                }
             }
         }
-        None
+        best.map(|(token, rule)| (token.to_string(), rule.token_type.clone()))
     }
-    fn match_token(&self, regex: &str, data_to_parse: &str) -> Option<String> {
-        if let Some(matched) = Regex::new(regex).unwrap().find(data_to_parse) {
-            return Some(matched.as_str().to_string());
-        }
-        None
+    // Only non-empty matches count, the rules are anchored at the cursor.
+    fn match_token<'a>(&self, regex: &Regex, data_to_parse: &'a str) -> Option<&'a str> {
+        regex
+            .find(data_to_parse)
+            .filter(|matched| !matched.as_str().is_empty())
+            .map(|matched| matched.as_str())
     }
     fn parse(&mut self, value: &str) -> ParsedValue {
         if let Ok(parsed) = value.parse::<u128>() {
@@ -70,3 +178,98 @@ impl Tokenizer {
         ParsedValue::String(value.to_string())
     }
 }
+
+fn unanchored(pattern: &str) -> &str {
+    pattern.strip_prefix('^').unwrap_or(pattern)
+}
+
+// The text matched by a pattern without any regex operator, e.g. `^\+\+`.
+fn literal(pattern: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut characters = unanchored(pattern).chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => match characters.next() {
+                Some(escaped) if !escaped.is_alphanumeric() => text.push(escaped),
+                _ => return None,
+            },
+            _ if "^$.|?*+()[]{}".contains(character) => return None,
+            _ => text.push(character),
+        }
+    }
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenizer(rules: &[(&str, TokenType)], to_parse: &str, policy: MatchPolicy) -> Tokenizer {
+        let spec = rules
+            .iter()
+            .map(|(pattern, token_type)| (pattern.to_string(), token_type.clone()))
+            .collect();
+        Tokenizer::with_policy(spec, to_parse.to_owned(), policy)
+    }
+
+    fn types(mut tokenizer: Tokenizer) -> Vec<TokenType> {
+        std::iter::from_fn(|| tokenizer.get_next_token())
+            .map(|token| token.token_type)
+            .collect()
+    }
+
+    #[test]
+    fn test_longest_match_wins_over_spec_order() {
+        let rules = [
+            (r"^\s+", TokenType::Null),
+            (r"^\d", TokenType::String),
+            (r"^\d+", TokenType::Number),
+        ];
+        let tokenizer = tokenizer(&rules, "123 4", MatchPolicy::LongestMatch);
+        assert_eq!(types(tokenizer), vec![TokenType::Number, TokenType::String]);
+    }
+
+    #[test]
+    fn test_first_match_follows_spec_order() {
+        let rules = [
+            (r"^\s+", TokenType::Null),
+            (r"^\d", TokenType::String),
+            (r"^\d+", TokenType::Number),
+        ];
+        let tokenizer = tokenizer(&rules, "12", MatchPolicy::FirstMatch);
+        assert_eq!(types(tokenizer), vec![TokenType::String, TokenType::String]);
+    }
+
+    #[test]
+    fn test_spec_warnings() {
+        let tokenizer = tokenizer(
+            &[
+                (r"^\s*", TokenType::Null),
+                (r"\d+", TokenType::Number),
+                (r"^\d+", TokenType::String),
+                (r"^42", TokenType::String),
+            ],
+            "",
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(
+            tokenizer.warnings(),
+            &[
+                SpecWarning::MatchesEmpty {
+                    pattern: r"^\s*".to_owned()
+                },
+                SpecWarning::MissingAnchor {
+                    pattern: r"\d+".to_owned()
+                },
+                SpecWarning::Unreachable {
+                    pattern: r"^\d+".to_owned(),
+                    shadowed_by: 1
+                },
+                SpecWarning::Unreachable {
+                    pattern: r"^42".to_owned(),
+                    shadowed_by: 1
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+// Compiles the rules of `lexer.toml` into the tables used by `DfaTokenizer`.
+
+use std::{cmp::Reverse, env, fmt::Write, fs, path::Path};
+
+use serde::Deserialize;
+
+#[allow(dead_code)]
+#[path = "src/lexgen.rs"]
+mod lexgen;
+
+#[derive(Deserialize)]
+struct LexerConfig {
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Deserialize)]
+struct RuleConfig {
+    regex: String,
+    kind: Option<String>,
+    #[serde(default)]
+    skip: bool,
+    #[serde(default)]
+    priority: i32,
+    mode: Option<String>,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=lexer.toml");
+    println!("cargo:rerun-if-changed=src/lexgen.rs");
+    let config: LexerConfig =
+        toml::from_str(&fs::read_to_string("lexer.toml").expect("Failed to read lexer.toml"))
+            .expect("Invalid lexer.toml");
+    // Same ordering as `LexerConfig::spec` for the default mode.
+    let mut rules: Vec<&RuleConfig> = config
+        .rules
+        .iter()
+        .filter(|rule| rule.mode.as_deref().unwrap_or("default") == "default")
+        .collect();
+    rules.sort_by_key(|rule| Reverse(rule.priority));
+    let patterns: Vec<&str> = rules.iter().map(|rule| rule.regex.as_str()).collect();
+    let dfa = lexgen::generate(&patterns).unwrap_or_else(|e| panic!("lexer.toml: {:?}", e));
+    for conflict in dfa.conflicts.iter() {
+        println!("cargo:warning=lexer.toml: {:?}", conflict);
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by build.rs from lexer.toml.").unwrap();
+    writeln!(out, "const CLASS_COUNT: usize = {};", dfa.class_count).unwrap();
+    writeln!(out, "static CLASSES: [u8; 256] = {:?};", dfa.classes).unwrap();
+    writeln!(
+        out,
+        "static TRANSITIONS: [u16; {}] = {:?};",
+        dfa.transitions.len(),
+        dfa.transitions
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "static ACCEPT: [Option<usize>; {}] = {:?};",
+        dfa.accept.len(),
+        dfa.accept
+    )
+    .unwrap();
+    let kinds: Vec<String> = rules
+        .iter()
+        .map(|rule| match (&rule.kind, rule.skip) {
+            (Some(kind), false) => format!("Some(TokenType::{})", kind),
+            _ => "None".to_owned(),
+        })
+        .collect();
+    writeln!(
+        out,
+        "static RULES: [Option<TokenType>; {}] = [{}];",
+        kinds.len(),
+        kinds.join(", ")
+    )
+    .unwrap();
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("dfa_tables.rs");
+    fs::write(path, out).expect("Failed to write the DFA tables");
+}
@@ -0,0 +1,341 @@
+// Structural diff between two programs. Nodes are compared with
+// `EqIgnoreSpan`, so moving code around or changing its layout and comments
+// only shows where the tree itself changed.
+//
+// Paths such as `body[2].expression.value` point into the new program, except
+// for deleted nodes and the origin of moved ones which point into the old
+// one.
+
+use std::collections::HashMap;
+
+use crate::{
+    models::{EqIgnoreSpan, Expression, Literal, Program, Statement},
+    sexpr::node_to_sexpr,
+    visit::NodeRef,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Inserted {
+        path: String,
+        node: String,
+    },
+    Deleted {
+        path: String,
+        node: String,
+    },
+    // The same node at another position.
+    Moved {
+        from: String,
+        to: String,
+        node: String,
+    },
+    // A node kept in place whose content differs, `path` is the innermost
+    // node holding the difference.
+    Updated {
+        path: String,
+        before: String,
+        after: String,
+    },
+}
+
+// Changes in program order. Statements kept in order are matched first, then
+// the remaining ones equal to a statement elsewhere count as moved. What is
+// left between two kept statements is paired up as updates, the extra ones
+// are insertions or deletions. Within such a gap, a change to the old
+// program comes before one to the new program at the same offset.
+pub fn diff(before: &Program, after: &Program) -> Vec<Change> {
+    let (old, new) = (&before.body, &after.body);
+    let kept = kept_in_order(old, new);
+    let mut old_used = vec![false; old.len()];
+    let mut new_used = vec![false; new.len()];
+    for (i, j) in kept.iter() {
+        old_used[*i] = true;
+        new_used[*j] = true;
+    }
+    // Origin of the statements moved to each position of `new`.
+    let mut moved: HashMap<usize, usize> = HashMap::new();
+    for j in 0..new.len() {
+        if new_used[j] {
+            continue;
+        }
+        if let Some(i) = (0..old.len()).find(|i| !old_used[*i] && old[*i].eq_ignore_span(&new[j])) {
+            old_used[i] = true;
+            moved.insert(j, i);
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut old_start, mut new_start) = (0, 0);
+    for (old_end, new_end) in kept
+        .into_iter()
+        .chain(std::iter::once((old.len(), new.len())))
+    {
+        let deleted: Vec<usize> = (old_start..old_end).filter(|i| !old_used[*i]).collect();
+        let inserted: Vec<usize> = (new_start..new_end)
+            .filter(|j| !new_used[*j] && !moved.contains_key(j))
+            .collect();
+        let paired = deleted.len().min(inserted.len());
+        // Changes of the gap keyed by their offset in it, old ones first.
+        let mut gap: Vec<(usize, Change)> = Vec::new();
+        for i in deleted[paired..].iter() {
+            gap.push((
+                2 * (i - old_start),
+                Change::Deleted {
+                    path: path(*i),
+                    node: node_to_sexpr(NodeRef::Statement(&old[*i])),
+                },
+            ));
+        }
+        for (i, j) in deleted.iter().zip(inserted.iter()) {
+            let mut updates = Vec::new();
+            diff_statement(&path(*j), &old[*i], &new[*j], &mut updates);
+            gap.extend(
+                updates
+                    .into_iter()
+                    .map(|update| (2 * (j - new_start) + 1, update)),
+            );
+        }
+        for (j, statement) in new.iter().enumerate().take(new_end).skip(new_start) {
+            if let Some(i) = moved.get(&j) {
+                gap.push((
+                    2 * (j - new_start) + 1,
+                    Change::Moved {
+                        from: path(*i),
+                        to: path(j),
+                        node: node_to_sexpr(NodeRef::Statement(statement)),
+                    },
+                ));
+            } else if inserted[paired..].contains(&j) {
+                gap.push((
+                    2 * (j - new_start) + 1,
+                    Change::Inserted {
+                        path: path(j),
+                        node: node_to_sexpr(NodeRef::Statement(statement)),
+                    },
+                ));
+            }
+        }
+        gap.sort_by_key(|(offset, _)| *offset);
+        changes.extend(gap.into_iter().map(|(_, change)| change));
+        old_start = old_end + 1;
+        new_start = new_end + 1;
+    }
+    changes
+}
+
+// One line per change, `~` updated, `-` deleted, `+` inserted, `>` moved.
+pub fn summary(changes: &[Change]) -> String {
+    if changes.is_empty() {
+        return "no changes\n".to_owned();
+    }
+    changes
+        .iter()
+        .map(|change| match change {
+            Change::Inserted { path, node } => format!("+ {}: {}\n", path, node),
+            Change::Deleted { path, node } => format!("- {}: {}\n", path, node),
+            Change::Moved { from, to, node } => format!("> {} -> {}: {}\n", from, to, node),
+            Change::Updated {
+                path,
+                before,
+                after,
+            } => format!("~ {}: {} -> {}\n", path, before, after),
+        })
+        .collect()
+}
+
+fn path(index: usize) -> String {
+    format!("body[{}]", index)
+}
+
+fn diff_statement(path: &str, before: &Statement, after: &Statement, changes: &mut Vec<Change>) {
+    match (before, after) {
+        (Statement::Expression(before), Statement::Expression(after)) => diff_expression(
+            &format!("{}.expression", path),
+            &before.expression,
+            &after.expression,
+            changes,
+        ),
+    }
+}
+
+fn diff_expression(path: &str, before: &Expression, after: &Expression, changes: &mut Vec<Change>) {
+    match (before, after) {
+        (Expression::Literal(before), Expression::Literal(after)) => {
+            diff_literal(path, before, after, changes)
+        }
+    }
+}
+
+fn diff_literal(path: &str, before: &Literal, after: &Literal, changes: &mut Vec<Change>) {
+    if before.eq_ignore_span(after) {
+        return;
+    }
+    let same_kind = matches!(
+        (before, after),
+        (Literal::Numeric(_), Literal::Numeric(_)) | (Literal::String(_), Literal::String(_))
+    );
+    // A literal of another kind replaces the node, else only its value
+    // changes.
+    let path = if same_kind {
+        format!("{}.value", path)
+    } else {
+        path.to_owned()
+    };
+    changes.push(Change::Updated {
+        path,
+        before: node_to_sexpr(NodeRef::Literal(before)),
+        after: node_to_sexpr(NodeRef::Literal(after)),
+    });
+}
+
+// Pairs of indices of the longest run of statements kept in the same order,
+// from a longest common subsequence. The common prefix and suffix are
+// matched first so that small edits to long programs stay cheap.
+fn kept_in_order(old: &[Statement], new: &[Statement]) -> Vec<(usize, usize)> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(old, new)| old.eq_ignore_span(new))
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old.eq_ignore_span(new))
+        .count();
+    let mut kept: Vec<(usize, usize)> = (0..prefix).map(|index| (index, index)).collect();
+    common(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+        (prefix, prefix),
+        &mut kept,
+    );
+    kept.extend((0..suffix).map(|index| (old.len() - suffix + index, new.len() - suffix + index)));
+    kept
+}
+
+// Hirschberg's algorithm: splits `old` in half and `new` where the common
+// subsequences of both halves add up to the longest one, so only a row of
+// lengths is kept in memory. `offset` is where both slices start.
+fn common(
+    old: &[Statement],
+    new: &[Statement],
+    offset: (usize, usize),
+    kept: &mut Vec<(usize, usize)>,
+) {
+    match old {
+        [] => {}
+        [statement] => {
+            if let Some(j) = new.iter().position(|new| statement.eq_ignore_span(new)) {
+                kept.push((offset.0, offset.1 + j));
+            }
+        }
+        _ => {
+            let middle = old.len() / 2;
+            let forward = lengths(old[..middle].iter(), new.iter());
+            let backward = lengths(old[middle..].iter().rev(), new.iter().rev());
+            let split = (0..=new.len())
+                .max_by_key(|j| forward[*j] + backward[new.len() - j])
+                .unwrap_or(0);
+            common(&old[..middle], &new[..split], offset, kept);
+            common(
+                &old[middle..],
+                &new[split..],
+                (offset.0 + middle, offset.1 + split),
+                kept,
+            );
+        }
+    }
+}
+
+// `lengths[j]` is the length of the common subsequence of `old` and the
+// first `j` statements of `new`.
+fn lengths<'a, I>(old: I, new: I) -> Vec<usize>
+where
+    I: Iterator<Item = &'a Statement> + Clone,
+{
+    let mut lengths = vec![0; new.clone().count() + 1];
+    for old in old {
+        let mut diagonal = 0;
+        for (j, new) in new.clone().enumerate() {
+            let above = lengths[j + 1];
+            lengths[j + 1] = if old.eq_ignore_span(new) {
+                diagonal + 1
+            } else {
+                above.max(lengths[j])
+            };
+            diagonal = above;
+        }
+    }
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_factory::ParserFactory;
+
+    fn changes(before: &str, after: &str) -> String {
+        let parse = |source: &str| ParserFactory::create(source.to_owned()).parse().unwrap();
+        summary(&diff(&parse(before), &parse(after)))
+    }
+
+    #[test]
+    fn test_layout_and_comments_are_ignored() {
+        assert_eq!(
+            changes("1; 'a';", "// header\n1;;\n\n  \"a\"; /* end */"),
+            "no changes\n"
+        );
+    }
+
+    #[test]
+    fn test_inserted_and_deleted() {
+        assert_eq!(
+            changes("1; 2; 3;", "1; 3; 4;"),
+            "- body[1]: (expr-stmt 2)\n+ body[2]: (expr-stmt 4)\n"
+        );
+    }
+
+    #[test]
+    fn test_updated() {
+        assert_eq!(
+            changes("1; 'a'; 3;", "1; 'b'; '3';"),
+            "\
+~ body[1].expression.value: \"a\" -> \"b\"
+~ body[2].expression: 3 -> \"3\"
+"
+        );
+        assert_eq!(
+            changes("1; 2; 3;", "'a'; 3;"),
+            "~ body[0].expression: 1 -> \"a\"\n- body[1]: (expr-stmt 2)\n"
+        );
+    }
+
+    #[test]
+    fn test_moved() {
+        assert_eq!(
+            changes("'use strict'; 1; 2; 3;", "'use strict'; 2; 3; 1;"),
+            "> body[1] -> body[3]: (expr-stmt 1)\n"
+        );
+        assert_eq!(
+            changes("1; 2; 3; 4;", "4; 2; 3; 5;"),
+            "\
+- body[0]: (expr-stmt 1)
+> body[3] -> body[0]: (expr-stmt 4)
++ body[3]: (expr-stmt 5)
+"
+        );
+    }
+
+    #[test]
+    fn test_long_programs() {
+        let program = |count: usize, step: usize| -> String {
+            (0..count).map(|n| format!("{};", n * step)).collect()
+        };
+        // Every other statement kept, a full table would hold 18 million lengths.
+        let changes = changes(&program(6_000, 1), &program(3_000, 2));
+        assert_eq!(changes.lines().count(), 3_000);
+        assert!(changes.lines().all(|line| line.starts_with("- ")));
+    }
+}
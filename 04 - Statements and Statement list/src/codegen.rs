@@ -0,0 +1,267 @@
+use crate::models::{DomainError, Expression, Literal, Program, Span, Statement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    #[default]
+    Double,
+    Single,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CodegenOptions {
+    // Preferred quote, the other one is used when the value contains it.
+    pub quote: QuoteStyle,
+    // Drops the line breaks between statements.
+    pub compact: bool,
+}
+
+// Binding strength of an expression, higher binds tighter. Literals are the
+// only expressions of the grammar so far, operators slot in below `Primary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Lowest,
+    Primary,
+}
+
+impl Expression {
+    pub fn precedence(&self) -> Precedence {
+        match self {
+            Expression::Literal(_) => Precedence::Primary,
+        }
+    }
+}
+
+// Byte offset in the printed code where the node starting at `original`
+// in the parsed source was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mapping {
+    pub generated: usize,
+    pub original: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Printed {
+    pub code: String,
+    // Ordered by `generated`, nodes with an empty span are not mapped.
+    pub mappings: Vec<Mapping>,
+}
+
+// Prints `program` back to source, parsing the output gives the same tree
+// apart from spans. The exception is an empty program, which the parser never
+// produces: it prints as nothing, and the grammar needs at least one
+// statement.
+pub fn print(program: &Program, options: &CodegenOptions) -> Result<String, DomainError> {
+    Ok(print_with_mappings(program, options)?.code)
+}
+
+pub fn print_with_mappings(
+    program: &Program,
+    options: &CodegenOptions,
+) -> Result<Printed, DomainError> {
+    let mut codegen = Codegen {
+        options,
+        output: String::new(),
+        mappings: Vec::new(),
+    };
+    codegen.program(program)?;
+    Ok(Printed {
+        code: codegen.output,
+        mappings: codegen.mappings,
+    })
+}
+
+struct Codegen<'a> {
+    options: &'a CodegenOptions,
+    output: String,
+    mappings: Vec<Mapping>,
+}
+
+impl Codegen<'_> {
+    fn map(&mut self, span: Span) {
+        let generated = self.output.len();
+        if span.is_empty()
+            || self
+                .mappings
+                .last()
+                .is_some_and(|mapping| mapping.generated == generated)
+        {
+            return;
+        }
+        self.mappings.push(Mapping {
+            generated,
+            original: span.start,
+        });
+    }
+    fn program(&mut self, program: &Program) -> Result<(), DomainError> {
+        for statement in program.body.iter() {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+    fn statement(&mut self, statement: &Statement) -> Result<(), DomainError> {
+        self.map(statement.span());
+        match statement {
+            Statement::Expression(statement) => {
+                self.expression(&statement.expression, Precedence::Lowest)?;
+                self.output.push(';');
+            }
+        }
+        if !self.options.compact {
+            self.output.push('\n');
+        }
+        Ok(())
+    }
+    // Wraps `expression` in parentheses when it binds looser than its context
+    // requires.
+    fn expression(
+        &mut self,
+        expression: &Expression,
+        precedence: Precedence,
+    ) -> Result<(), DomainError> {
+        let parenthesize = expression.precedence() < precedence;
+        if parenthesize {
+            self.output.push('(');
+        }
+        self.map(expression.span());
+        match expression {
+            Expression::Literal(Literal::Numeric(literal)) => {
+                self.output.push_str(&literal.value.to_string())
+            }
+            Expression::Literal(Literal::String(literal)) => {
+                let quoted = quote(&literal.value, self.options.quote)?;
+                self.output.push_str(&quoted)
+            }
+        }
+        if parenthesize {
+            self.output.push(')');
+        }
+        Ok(())
+    }
+}
+
+// String literals have no escape sequences, the value is kept verbatim
+// between quotes it doesn't contain.
+pub fn quote(value: &str, style: QuoteStyle) -> Result<String, DomainError> {
+    let (preferred, other) = match style {
+        QuoteStyle::Double => ('"', '\''),
+        QuoteStyle::Single => ('\'', '"'),
+    };
+    let quote = if !value.contains(preferred) {
+        preferred
+    } else if !value.contains(other) {
+        other
+    } else {
+        return Err(DomainError::UnquotableString(value.to_owned()));
+    };
+    Ok(format!("{}{}{}", quote, value, quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::{
+        models::{EqIgnoreSpan, ExpressionStatement, Span, StringLiteral},
+        parser_factory::ParserFactory,
+    };
+
+    fn parse(to_parse: &str) -> Program {
+        ParserFactory::create(to_parse.to_owned()).parse().unwrap()
+    }
+
+    fn corpus() -> Vec<String> {
+        let mut corpus: Vec<String> = [
+            "42;",
+            " ' test : ';\n   42;",
+            "// comment\n42;;; 'a'; /* multi\n * line */ \"b\";",
+            "'say \"hi\"'; \"it's\";",
+            "'back\\slash'; 'multi\nline';",
+            "'héllo wörld'; '';",
+            "340282366920938463463374607431768211455;",
+        ]
+        .iter()
+        .map(|source| source.to_string())
+        .collect();
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/estree");
+        for entry in fs::read_dir(fixtures).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) == Some("js") {
+                corpus.push(fs::read_to_string(path).unwrap());
+            }
+        }
+        corpus
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let styles = [
+            CodegenOptions::default(),
+            CodegenOptions {
+                quote: QuoteStyle::Single,
+                compact: false,
+            },
+            CodegenOptions {
+                compact: true,
+                ..CodegenOptions::default()
+            },
+        ];
+        for source in corpus() {
+            let program = parse(&source);
+            for options in styles.iter() {
+                let printed = print(&program, options).unwrap();
+                assert!(
+                    parse(&printed).eq_ignore_span(&program),
+                    "{:?} printed as {:?}",
+                    source,
+                    printed
+                );
+                // Printing is stable once the input is in printed form.
+                assert_eq!(print(&parse(&printed), options).unwrap(), printed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quote_style() {
+        let program = parse("'a'; \"b\"; 'say \"hi\"'; 7;");
+        assert_eq!(
+            print(&program, &CodegenOptions::default()).unwrap(),
+            "\"a\";\n\"b\";\n'say \"hi\"';\n7;\n"
+        );
+        let single = CodegenOptions {
+            quote: QuoteStyle::Single,
+            ..CodegenOptions::default()
+        };
+        assert_eq!(
+            print(&program, &single).unwrap(),
+            "'a';\n'b';\n'say \"hi\"';\n7;\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let program = Program::new(vec![], Span::default());
+        assert_eq!(print(&program, &CodegenOptions::default()).unwrap(), "");
+        assert!(ParserFactory::create(String::new()).parse().is_err());
+    }
+
+    #[test]
+    fn test_unquotable_string() {
+        let value = "it's \"quoted\"".to_owned();
+        let program = Program::new(
+            vec![Statement::Expression(ExpressionStatement {
+                expression: Expression::Literal(Literal::String(StringLiteral {
+                    value: value.clone(),
+                    span: Span::default(),
+                })),
+                span: Span::default(),
+            })],
+            Span::default(),
+        );
+        assert_eq!(
+            print(&program, &CodegenOptions::default()),
+            Err(DomainError::UnquotableString(value))
+        );
+    }
+}
@@ -0,0 +1,809 @@
+// Lossless concrete syntax tree. Every byte of the input, whitespace,
+// comments and unparsable text included, is in exactly one token, so
+// printing the tree gives back the input.
+//
+// The tree comes in two layers. Green nodes hold kinds and text only, they
+// are immutable and can be shared between trees. Red nodes (`SyntaxNode`)
+// wrap a green node with its offset and parent, and are built on demand
+// while walking down. Typed wrappers such as `ProgramSyntax` give an AST
+// view over the red nodes.
+//
+// The tree is recorded by `Parser` itself, see `Parser::with_syntax_tree`,
+// so it follows the same grammar as `Program`.
+
+use std::{fmt, rc::Rc};
+
+use crate::{
+    models::{
+        DomainError, Expression, ExpressionStatement, Literal, NumericLiteral, Program, Span,
+        Statement, StringLiteral,
+    },
+    parser::ParseOptions,
+    parser_factory::ParserFactory,
+    tokenizer::TokenType,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // Nodes.
+    Program,
+    ExpressionStatement,
+    NumericLiteral,
+    StringLiteral,
+    // Tokens.
+    Number,
+    String,
+    SemiColon,
+    Whitespace,
+    Comment,
+    // A node around a statement that failed to parse, or a token of text the
+    // tokenizer stopped at.
+    Error,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+    fn from_token_type(token_type: &TokenType) -> SyntaxKind {
+        match token_type {
+            TokenType::Number => SyntaxKind::Number,
+            TokenType::String => SyntaxKind::String,
+            TokenType::SemiColon => SyntaxKind::SemiColon,
+            TokenType::Null => SyntaxKind::Whitespace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    // Length of the text in bytes.
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        let width = children.iter().map(GreenElement::width).sum();
+        GreenNode {
+            kind,
+            width,
+            children,
+        }
+    }
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.children.iter().try_for_each(|child| match child {
+            GreenElement::Node(node) => write!(f, "{}", node),
+            GreenElement::Token(token) => f.write_str(&token.text),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind,
+            GreenElement::Token(token) => token.kind,
+        }
+    }
+    pub fn width(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.width,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+// Records a green tree from the parser's calls. Trivia is held back until
+// the next token or node: trivia before a node goes to its parent, and the
+// trivia at the end of the input goes to the root.
+pub(crate) struct GreenBuilder {
+    source: String,
+    // End of the text recorded so far.
+    offset: usize,
+    trivia: Vec<Span>,
+    // Open nodes with their children so far, the innermost last.
+    stack: Vec<(SyntaxKind, Vec<GreenElement>)>,
+    root: Option<GreenNode>,
+}
+
+impl GreenBuilder {
+    pub(crate) fn new(source: String) -> GreenBuilder {
+        GreenBuilder {
+            source,
+            offset: 0,
+            trivia: vec![],
+            stack: vec![],
+            root: None,
+        }
+    }
+    // Number of open nodes.
+    pub(crate) fn depth(&self) -> usize {
+        self.stack.len()
+    }
+    pub(crate) fn start_node(&mut self, kind: SyntaxKind) {
+        if !self.stack.is_empty() {
+            self.flush_trivia();
+        }
+        self.stack.push((kind, vec![]));
+    }
+    pub(crate) fn finish_node(&mut self) {
+        if self.stack.len() == 1 {
+            self.flush_trivia();
+        }
+        let (kind, children) = self.stack.pop().expect("a node is open");
+        let node = GreenNode::new(kind, children);
+        match self.stack.last_mut() {
+            Some((_, siblings)) => siblings.push(GreenElement::Node(Rc::new(node))),
+            None => self.root = Some(node),
+        }
+    }
+    // Turns the node open at `depth`, the one started when the builder had
+    // that depth, into an error node and closes the nodes inside it. The
+    // tokens skipped by the parser are then added to it.
+    pub(crate) fn start_error(&mut self, depth: usize) {
+        if self.stack.len() <= depth {
+            self.start_node(SyntaxKind::Error);
+            return;
+        }
+        while self.stack.len() > depth + 1 {
+            self.finish_node();
+        }
+        self.stack[depth].0 = SyntaxKind::Error;
+    }
+    pub(crate) fn trivia(&mut self, spans: Vec<Span>) {
+        self.trivia.extend(spans);
+    }
+    pub(crate) fn token(&mut self, token_type: &TokenType, span: Span) {
+        self.flush_trivia();
+        self.push_token(SyntaxKind::from_token_type(token_type), span);
+    }
+    // The root node. Nodes still open were cut short by a tokenizer error and
+    // are closed as error nodes, the input not recorded becomes an error token
+    // of the root.
+    pub(crate) fn finish(mut self) -> GreenNode {
+        match self.root.take() {
+            Some(root) => self.stack.push((root.kind, root.children)),
+            None if self.stack.is_empty() => self.stack.push((SyntaxKind::Program, vec![])),
+            None => {}
+        }
+        while let [_, .., (kind, _)] = self.stack.as_mut_slice() {
+            *kind = SyntaxKind::Error;
+            self.finish_node();
+        }
+        self.flush_trivia();
+        if self.offset < self.source.len() {
+            let span = Span::new(self.offset, self.source.len());
+            self.push_token(SyntaxKind::Error, span);
+        }
+        let (kind, children) = self.stack.pop().expect("the root is open");
+        GreenNode::new(kind, children)
+    }
+    fn flush_trivia(&mut self) {
+        for span in std::mem::take(&mut self.trivia) {
+            let text = &self.source[span.start..span.end];
+            let kind = if text.trim().is_empty() {
+                SyntaxKind::Whitespace
+            } else {
+                SyntaxKind::Comment
+            };
+            self.push_token(kind, span);
+        }
+    }
+    fn push_token(&mut self, kind: SyntaxKind, span: Span) {
+        // Text the tokenizer didn't report is kept as an error token.
+        if span.start > self.offset {
+            let gap = Span::new(self.offset, span.start);
+            self.push_token(SyntaxKind::Error, gap);
+        }
+        let token = GreenToken {
+            kind,
+            text: self.source[span.start..span.end].to_owned(),
+        };
+        self.offset = span.end;
+        self.stack
+            .last_mut()
+            .expect("a node is open")
+            .1
+            .push(GreenElement::Token(Rc::new(token)));
+    }
+}
+
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: GreenNode) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData {
+            green: Rc::new(green),
+            offset: 0,
+            parent: None,
+        }))
+    }
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+    pub fn green(&self) -> &GreenNode {
+        &self.0.green
+    }
+    pub fn span(&self) -> Span {
+        Span::new(self.0.offset, self.0.offset + self.0.green.width)
+    }
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|child| {
+                let start = offset;
+                offset += child.width();
+                match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: green.clone(),
+                            offset: start,
+                            parent: Some(self.clone()),
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        offset: start,
+                        parent: self.clone(),
+                    }),
+                }
+            })
+            .collect()
+    }
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+    pub fn child_tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(_) => None,
+                SyntaxElement::Token(token) => Some(token),
+            })
+            .collect()
+    }
+    // All the tokens under the node, in source order.
+    pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .flat_map(|child| match child {
+                SyntaxElement::Node(node) => node.descendant_tokens(),
+                SyntaxElement::Token(token) => vec![token],
+            })
+            .collect()
+    }
+    // The token holding the byte at `offset`.
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .find(|child| {
+                let span = child.span();
+                span.start <= offset && offset < span.end
+            })
+            .and_then(|child| match child {
+                SyntaxElement::Node(node) => node.token_at_offset(offset),
+                SyntaxElement::Token(token) => Some(token),
+            })
+    }
+    // One line per node and token, indented by depth, e.g.
+    // `NumericLiteral@0..2` followed by `  Number@0..2 "42"`.
+    pub fn debug_tree(&self) -> String {
+        let mut output = String::new();
+        self.write_tree(0, &mut output);
+        output
+    }
+    fn write_tree(&self, depth: usize, output: &mut String) {
+        output.push_str(&format!("{}{:?}\n", "  ".repeat(depth), self));
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => node.write_tree(depth + 1, output),
+                SyntaxElement::Token(token) => {
+                    output.push_str(&format!("{}{:?}\n", "  ".repeat(depth + 1), token))
+                }
+            }
+        }
+    }
+}
+
+// Two nodes are equal when they are the same node of the same tree.
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &SyntaxNode) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{:?}@{}..{}", self.kind(), span.start, span.end)
+    }
+}
+
+// The text of the node, trivia included.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.offset + self.green.text.len())
+    }
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(
+            f,
+            "{:?}@{}..{} {:?}",
+            self.kind(),
+            span.start,
+            span.end,
+            self.text()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span(),
+            SyntaxElement::Token(token) => token.span(),
+        }
+    }
+}
+
+// A parsed input with the errors met on the way. The tree holds the whole
+// input even when there are errors.
+#[derive(Debug, Clone)]
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    pub errors: Vec<DomainError>,
+}
+
+impl SyntaxTree {
+    pub fn program(&self) -> ProgramSyntax {
+        ProgramSyntax::cast(self.root.clone()).expect("the root is a program")
+    }
+}
+
+// Parses with the bundled spec. Syntax errors are always recovered from, see
+// `ParseOptions::recover`.
+pub fn parse(source: &str, options: ParseOptions) -> SyntaxTree {
+    let options = ParseOptions {
+        recover: true,
+        ..options
+    };
+    let mut parser = ParserFactory::create(source.to_owned())
+        .with_options(options)
+        .with_syntax_tree(source.to_owned());
+    let parsed = parser.parse();
+    let mut errors = parser.errors().to_vec();
+    errors.extend(parsed.err());
+    let green = parser
+        .finish_syntax_tree()
+        .expect("the parser records a syntax tree");
+    SyntaxTree {
+        root: SyntaxNode::new_root(green),
+        errors,
+    }
+}
+
+// A typed view of a node of some kind.
+pub trait AstNode: Sized {
+    fn cast(node: SyntaxNode) -> Option<Self>;
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramSyntax(SyntaxNode);
+
+impl AstNode for ProgramSyntax {
+    fn cast(node: SyntaxNode) -> Option<ProgramSyntax> {
+        (node.kind() == SyntaxKind::Program).then_some(ProgramSyntax(node))
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+impl ProgramSyntax {
+    // The statements parsed without error.
+    pub fn statements(&self) -> Vec<ExpressionStatementSyntax> {
+        self.0
+            .children()
+            .into_iter()
+            .filter_map(ExpressionStatementSyntax::cast)
+            .collect()
+    }
+    // Nodes of the statements that failed to parse.
+    pub fn errors(&self) -> Vec<SyntaxNode> {
+        self.0
+            .children()
+            .into_iter()
+            .filter(|node| node.kind() == SyntaxKind::Error)
+            .collect()
+    }
+    // The program `Parser::parse` gives for the same input.
+    pub fn to_ast(&self) -> Program {
+        let body = self
+            .statements()
+            .iter()
+            .filter_map(|statement| statement.to_ast().map(Statement::Expression))
+            .collect();
+        Program::new(body, self.0.span())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionStatementSyntax(SyntaxNode);
+
+impl AstNode for ExpressionStatementSyntax {
+    fn cast(node: SyntaxNode) -> Option<ExpressionStatementSyntax> {
+        (node.kind() == SyntaxKind::ExpressionStatement).then_some(ExpressionStatementSyntax(node))
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+impl ExpressionStatementSyntax {
+    pub fn literal(&self) -> Option<LiteralSyntax> {
+        self.0.children().into_iter().find_map(LiteralSyntax::cast)
+    }
+    // None when the semicolon was inserted, see `ParseOptions::asi`.
+    pub fn semicolon(&self) -> Option<SyntaxToken> {
+        self.0
+            .child_tokens()
+            .into_iter()
+            .find(|token| token.kind() == SyntaxKind::SemiColon)
+    }
+    pub fn to_ast(&self) -> Option<ExpressionStatement> {
+        let expression = Expression::Literal(self.literal()?.to_ast()?);
+        let end = self
+            .semicolon()
+            .map_or(expression.span().end, |semicolon| semicolon.span().end);
+        Some(ExpressionStatement {
+            span: Span::new(expression.span().start, end),
+            expression,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiteralSyntax {
+    Numeric(NumericLiteralSyntax),
+    String(StringLiteralSyntax),
+}
+
+impl AstNode for LiteralSyntax {
+    fn cast(node: SyntaxNode) -> Option<LiteralSyntax> {
+        match node.kind() {
+            SyntaxKind::NumericLiteral => Some(LiteralSyntax::Numeric(NumericLiteralSyntax(node))),
+            SyntaxKind::StringLiteral => Some(LiteralSyntax::String(StringLiteralSyntax(node))),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            LiteralSyntax::Numeric(literal) => literal.syntax(),
+            LiteralSyntax::String(literal) => literal.syntax(),
+        }
+    }
+}
+
+impl LiteralSyntax {
+    pub fn to_ast(&self) -> Option<Literal> {
+        match self {
+            LiteralSyntax::Numeric(literal) => literal.to_ast().map(Literal::Numeric),
+            LiteralSyntax::String(literal) => literal.to_ast().map(Literal::String),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericLiteralSyntax(SyntaxNode);
+
+impl AstNode for NumericLiteralSyntax {
+    fn cast(node: SyntaxNode) -> Option<NumericLiteralSyntax> {
+        (node.kind() == SyntaxKind::NumericLiteral).then_some(NumericLiteralSyntax(node))
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+impl NumericLiteralSyntax {
+    pub fn token(&self) -> Option<SyntaxToken> {
+        self.0
+            .child_tokens()
+            .into_iter()
+            .find(|token| token.kind() == SyntaxKind::Number)
+    }
+    pub fn value(&self) -> Option<u128> {
+        self.token()?.text().parse().ok()
+    }
+    pub fn to_ast(&self) -> Option<NumericLiteral> {
+        Some(NumericLiteral {
+            value: self.value()?,
+            span: self.token()?.span(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringLiteralSyntax(SyntaxNode);
+
+impl AstNode for StringLiteralSyntax {
+    fn cast(node: SyntaxNode) -> Option<StringLiteralSyntax> {
+        (node.kind() == SyntaxKind::StringLiteral).then_some(StringLiteralSyntax(node))
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+impl StringLiteralSyntax {
+    pub fn token(&self) -> Option<SyntaxToken> {
+        self.0
+            .child_tokens()
+            .into_iter()
+            .find(|token| token.kind() == SyntaxKind::String)
+    }
+    // The text between the quotes.
+    pub fn value(&self) -> Option<String> {
+        let token = self.token()?;
+        let text = token.text();
+        Some(text[1..text.len() - 1].to_owned())
+    }
+    pub fn to_ast(&self) -> Option<StringLiteral> {
+        Some(StringLiteral {
+            value: self.value()?,
+            span: self.token()?.span(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        let tree = parse("  42 /* c */;\n'a';// end\n", ParseOptions::default());
+        assert!(tree.errors.is_empty());
+        assert_eq!(
+            tree.root.debug_tree(),
+            r#"Program@0..25
+  Whitespace@0..2 "  "
+  ExpressionStatement@2..13
+    NumericLiteral@2..4
+      Number@2..4 "42"
+    Whitespace@4..5 " "
+    Comment@5..12 "/* c */"
+    SemiColon@12..13 ";"
+  Whitespace@13..14 "\n"
+  ExpressionStatement@14..18
+    StringLiteral@14..17
+      String@14..17 "'a'"
+    SemiColon@17..18 ";"
+  Comment@18..24 "// end"
+  Whitespace@24..25 "\n"
+"#
+        );
+    }
+
+    #[test]
+    fn test_recovered_errors() {
+        let source = "1 2; ;\n'a'";
+        let tree = parse(source, ParseOptions::default());
+        assert_eq!(tree.root.to_string(), source);
+        assert_eq!(tree.errors.len(), 3);
+        assert_eq!(
+            tree.root.debug_tree(),
+            r#"Program@0..10
+  Error@0..4
+    NumericLiteral@0..1
+      Number@0..1 "1"
+    Whitespace@1..2 " "
+    Number@2..3 "2"
+    SemiColon@3..4 ";"
+  Whitespace@4..5 " "
+  Error@5..6
+    SemiColon@5..6 ";"
+  Whitespace@6..7 "\n"
+  Error@7..10
+    StringLiteral@7..10
+      String@7..10 "'a'"
+"#
+        );
+        let program = tree.program();
+        assert!(program.statements().is_empty());
+        assert_eq!(program.errors().len(), 3);
+    }
+
+    #[test]
+    fn test_tokenizer_error() {
+        let source = "1; @ 2;";
+        let tree = parse(source, ParseOptions::default());
+        assert!(matches!(
+            tree.errors.as_slice(),
+            [DomainError::UnexpectedCharacter { character: '@', .. }]
+        ));
+        assert_eq!(
+            tree.root.debug_tree(),
+            r#"Program@0..7
+  ExpressionStatement@0..2
+    NumericLiteral@0..1
+      Number@0..1 "1"
+    SemiColon@1..2 ";"
+  Whitespace@2..3 " "
+  Error@3..7 "@ 2;"
+"#
+        );
+        // The statement cut short is an error node.
+        let tree = parse("1; 2 @", ParseOptions::default());
+        assert!(matches!(
+            tree.errors.as_slice(),
+            [DomainError::UnexpectedCharacter { character: '@', .. }]
+        ));
+        assert_eq!(
+            tree.root.debug_tree(),
+            r#"Program@0..6
+  ExpressionStatement@0..2
+    NumericLiteral@0..1
+      Number@0..1 "1"
+    SemiColon@1..2 ";"
+  Whitespace@2..3 " "
+  Error@3..4
+    NumericLiteral@3..4
+      Number@3..4 "2"
+  Whitespace@4..5 " "
+  Error@5..6 "@"
+"#
+        );
+        let tree = parse(" 1", ParseOptions::default());
+        assert_eq!(tree.root.to_string(), " 1");
+        assert_eq!(tree.program().errors().len(), 1);
+    }
+
+    #[test]
+    fn test_typed_view() {
+        let source = "1;\n  'a' // x\n 3";
+        let options = ParseOptions {
+            asi: true,
+            ..ParseOptions::default()
+        };
+        let tree = parse(source, options);
+        let program = tree.program();
+        let statements = program.statements();
+        assert_eq!(statements.len(), 3);
+        assert!(statements[1].semicolon().is_none());
+        assert!(matches!(
+            statements[2].literal(),
+            Some(LiteralSyntax::Numeric(literal)) if literal.value() == Some(3)
+        ));
+        let parsed = ParserFactory::create(source.to_owned())
+            .with_options(options)
+            .parse()
+            .unwrap();
+        assert_eq!(program.to_ast(), parsed);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let tree = parse("1; 'abc';", ParseOptions::default());
+        let token = tree.root.token_at_offset(5).unwrap();
+        assert_eq!(token.text(), "'abc'");
+        assert_eq!(token.kind(), SyntaxKind::String);
+        let statement = token.parent().parent().unwrap();
+        assert_eq!(statement.kind(), SyntaxKind::ExpressionStatement);
+        assert_eq!(statement.span(), Span::new(3, 9));
+        assert_eq!(statement.parent(), Some(tree.root.clone()));
+        assert!(tree.root.token_at_offset(9).is_none());
+    }
+
+    proptest! {
+        #[test]
+        fn test_lossless(source in "[0-9a '\";/*\n@]{0,24}") {
+            let tree = parse(&source, ParseOptions::default());
+            prop_assert_eq!(tree.root.to_string(), source.clone());
+            // The tokens cover the input end to end.
+            let mut offset = 0;
+            for token in tree.root.descendant_tokens() {
+                prop_assert_eq!(token.span().start, offset);
+                prop_assert!(!token.text().is_empty());
+                offset = token.span().end;
+            }
+            prop_assert_eq!(offset, source.len());
+        }
+    }
+}
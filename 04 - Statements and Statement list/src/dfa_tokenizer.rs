@@ -0,0 +1,197 @@
+use crate::{
+    lexgen::{DEAD, START},
+    models::{DomainError, Span},
+    tokenizer::{TokenSource, TokenType, TokenValue},
+};
+
+// Tables generated from `lexer.toml` by `build.rs` with `lexgen`.
+include!(concat!(env!("OUT_DIR"), "/dfa_tables.rs"));
+
+// Table-driven scanner for the bundled spec, produces the same tokens as
+// `Tokenizer` without compiling any regex at runtime. Only non-ASCII digits
+// differ: `Tokenizer` fails to convert them, this one doesn't read them, see
+// `lexgen` about `\d`.
+pub struct DfaTokenizer {
+    to_parse: String,
+    cursor: usize,
+    newline_before: bool,
+    // Spans of the skipped matches, when asked for.
+    trivia: Option<Vec<Span>>,
+}
+
+impl DfaTokenizer {
+    pub fn new(to_parse: String) -> DfaTokenizer {
+        DfaTokenizer {
+            to_parse,
+            cursor: 0,
+            newline_before: false,
+            trivia: None,
+        }
+    }
+    pub fn get_next_token(&mut self) -> Result<Option<TokenValue>, DomainError> {
+        self.newline_before = false;
+        let token = self.scan();
+        if token.is_err() {
+            self.cursor = self.to_parse.len();
+        }
+        token
+    }
+    fn scan(&mut self) -> Result<Option<TokenValue>, DomainError> {
+        while self.cursor < self.to_parse.len() {
+            let Some((length, rule)) = self.search_token() else {
+                return Err(DomainError::UnexpectedCharacter {
+                    character: self.to_parse[self.cursor..].chars().next().unwrap(),
+                    offset: self.cursor,
+                });
+            };
+            let span = Span::new(self.cursor, self.cursor + length);
+            let value = &self.to_parse[self.cursor..self.cursor + length];
+            self.cursor += length;
+            match &RULES[rule] {
+                Some(token_type) => {
+                    return Ok(Some(TokenValue {
+                        token_type: token_type.clone(),
+                        value: token_type.convert(value).map_err(|e| e.at(span))?,
+                        span,
+                    }))
+                }
+                None => {
+                    self.newline_before |= value.contains(['\n', '\r', '\u{2028}', '\u{2029}']);
+                    if let Some(trivia) = self.trivia.as_mut() {
+                        trivia.push(span);
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+    fn search_token(&self) -> Option<(usize, usize)> {
+        let mut state = START;
+        let mut found = None;
+        for (index, byte) in self.to_parse.as_bytes()[self.cursor..].iter().enumerate() {
+            state = TRANSITIONS[state as usize * CLASS_COUNT + CLASSES[*byte as usize] as usize];
+            if state == DEAD {
+                break;
+            }
+            if let Some(rule) = ACCEPT[state as usize] {
+                found = Some((index + 1, rule));
+            }
+        }
+        found
+    }
+}
+
+impl TokenSource for DfaTokenizer {
+    fn get_next_token(&mut self) -> Result<Option<TokenValue>, DomainError> {
+        DfaTokenizer::get_next_token(self)
+    }
+    fn position(&self) -> usize {
+        self.cursor
+    }
+    fn newline_before(&self) -> bool {
+        self.newline_before
+    }
+    fn keep_trivia(&mut self) {
+        self.trivia.get_or_insert_with(Vec::new);
+    }
+    fn take_trivia(&mut self) -> Vec<Span> {
+        self.trivia.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{LexerConfig, DEFAULT_MODE},
+        parser::ParseOptions,
+        parser_factory::ParserFactory,
+        tokenizer::Tokenizer,
+    };
+
+    fn assert_same_tokens(to_parse: &str) {
+        let spec = LexerConfig::from_toml(include_str!("../lexer.toml"))
+            .unwrap()
+            .spec(DEFAULT_MODE)
+            .unwrap();
+        let mut expected = Tokenizer::new(TokenType::rules(spec).unwrap(), to_parse.to_owned());
+        let mut actual = DfaTokenizer::new(to_parse.to_owned());
+        loop {
+            let token = expected.get_next_token();
+            assert_eq!(actual.get_next_token(), token, "input: {:?}", to_parse);
+            if !matches!(token, Ok(Some(_))) {
+                assert_eq!(actual.get_next_token(), Ok(None));
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_tokens_as_tokenizer() {
+        for to_parse in [
+            "42;",
+            r#" " test : ";"#,
+            " ' test : ';\n   42;",
+            "// comment\n42;;; 'a' /* multi\n * line */ \"b\";",
+            "/* a */ 1 /* b */ 2",
+            "'unterminated",
+            "/* unterminated",
+            "'héllo wörld';",
+            "// é ☃ 🦀\n1; /* ü\n */ \"ö\";",
+            "1; ☃",
+            "é",
+            "1;\u{a0}2;\u{2028}'\u{3000}';",
+            "1 abc",
+            "",
+            "340282366920938463463374607431768211456",
+        ] {
+            assert_same_tokens(to_parse);
+        }
+    }
+
+    #[test]
+    fn test_same_tokens_on_generated_inputs() {
+        let alphabet = [
+            "0", "7", "42", ";", "'", "\"", "/", "*", " ", "\n", "\t", "a", "é", "//", "/*", "*/",
+        ];
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..300 {
+            let mut to_parse = String::new();
+            for _ in 0..40 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                to_parse.push_str(alphabet[(seed % alphabet.len() as u64) as usize]);
+            }
+            assert_same_tokens(&to_parse);
+        }
+    }
+
+    #[test]
+    fn test_same_programs_as_tokenizer() {
+        let options = [
+            ParseOptions::default(),
+            ParseOptions {
+                recover: true,
+                asi: true,
+            },
+        ];
+        for to_parse in [
+            "42;",
+            "'use strict';\n// c\n1; /* x */ \"b\";",
+            "1\n2\u{2028}'a'",
+            "1 2; 3;",
+            "1; @ 2;",
+            "'unterminated",
+            "",
+        ] {
+            for options in options {
+                let mut expected = ParserFactory::create(to_parse.to_owned()).with_options(options);
+                let mut actual =
+                    ParserFactory::create_dfa(to_parse.to_owned()).with_options(options);
+                assert_eq!(actual.parse(), expected.parse(), "input: {:?}", to_parse);
+                assert_eq!(actual.errors(), expected.errors(), "input: {:?}", to_parse);
+            }
+        }
+    }
+}
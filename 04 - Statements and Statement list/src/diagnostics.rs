@@ -0,0 +1,564 @@
+use crate::{
+    models::{DomainError, Span},
+    tokenizer::TokenType,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+// A problem found in the source, reported without stopping the analysis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    // Stable identifier such as "E0002".
+    pub code: Option<&'static str>,
+    pub message: String,
+    // `None` for problems with no place in the source, like a missing file.
+    pub span: Option<Span>,
+    // Text written under the primary span.
+    pub label: Option<String>,
+    // Other nodes involved, such as the declaration a duplicate conflicts
+    // with.
+    pub related: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Span) -> Diagnostic {
+        Diagnostic {
+            span: Some(span),
+            ..Diagnostic::unlocated(message)
+        }
+    }
+    pub fn unlocated(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            level: Level::Error,
+            code: None,
+            message: message.into(),
+            span: None,
+            label: None,
+            related: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+    pub fn with_level(mut self, level: Level) -> Diagnostic {
+        self.level = level;
+        self
+    }
+    pub fn with_code(mut self, code: &'static str) -> Diagnostic {
+        self.code = Some(code);
+        self
+    }
+    pub fn with_label(mut self, label: impl Into<String>) -> Diagnostic {
+        self.label = Some(label.into());
+        self
+    }
+    pub fn with_related(mut self, span: Span, message: impl Into<String>) -> Diagnostic {
+        self.related.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+    pub fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+impl DomainError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            DomainError::UnexpectedCharacter { character, offset } => Diagnostic::new(
+                format!("unexpected character `{}`", character),
+                Span::new(*offset, offset + character.len_utf8()),
+            )
+            .with_code("E0001")
+            .with_label("no token starts with this character"),
+            DomainError::UnexpectedToken {
+                expected,
+                found,
+                span,
+            } => Diagnostic::new(
+                format!(
+                    "expected {}, found {}",
+                    describe_all(expected),
+                    describe(found)
+                ),
+                *span,
+            )
+            .with_code("E0002")
+            .with_label(format!("expected {}", describe_all(expected)))
+            .with_help(help_for(expected)),
+            DomainError::UnexpectedEndOfInput { expected, offset } => Diagnostic::new(
+                format!(
+                    "unexpected end of input, expected {}",
+                    describe_all(expected)
+                ),
+                Span::new(*offset, *offset),
+            )
+            .with_code("E0003")
+            .with_label(format!("expected {}", describe_all(expected)))
+            .with_help(help_for(expected)),
+            DomainError::InvalidUtf8 { offset } => Diagnostic::new(
+                "stream did not contain valid UTF-8",
+                Span::new(*offset, *offset),
+            )
+            .with_code("E0004")
+            .with_label("invalid byte sequence starts here"),
+            DomainError::ParseIntError => Diagnostic::unlocated("number literal is too large")
+                .with_code("E0005")
+                .with_note("numbers are stored as 128-bit unsigned integers"),
+            DomainError::NumberTooLarge { span } => {
+                Diagnostic::new("number literal is too large", *span)
+                    .with_code("E0005")
+                    .with_label("does not fit in 128 bits")
+                    .with_note("numbers are stored as 128-bit unsigned integers")
+            }
+            DomainError::ConfigIo(message) => {
+                Diagnostic::unlocated(format!("could not read the configuration: {}", message))
+                    .with_code("E0101")
+            }
+            DomainError::ConfigSyntax(message) => {
+                Diagnostic::unlocated(format!("invalid configuration: {}", message))
+                    .with_code("E0102")
+            }
+            DomainError::UnsupportedConfigFormat(path) => {
+                Diagnostic::unlocated(format!("unsupported configuration format: {}", path))
+                    .with_code("E0103")
+                    .with_help("use a .toml or .json file")
+            }
+            DomainError::InvalidRegex { regex, message } => {
+                Diagnostic::unlocated(format!("invalid regex `{}`", regex))
+                    .with_code("E0104")
+                    .with_note(message.clone())
+            }
+            DomainError::UnknownTokenKind(kind) => {
+                Diagnostic::unlocated(format!("unknown token kind `{}`", kind))
+                    .with_code("E0105")
+                    .with_help(format!("known kinds are {}", kind_names()))
+            }
+            DomainError::MissingTokenKind { regex } => {
+                Diagnostic::unlocated(format!("rule `{}` has no token kind", regex))
+                    .with_code("E0106")
+                    .with_help("set `kind`, or `skip = true` to drop the match")
+            }
+            DomainError::Io(message) => Diagnostic::unlocated(message.clone()).with_code("E0201"),
+            DomainError::UnquotableString(value) => {
+                Diagnostic::unlocated(format!("string {:?} contains both quote characters", value))
+                    .with_code("E0301")
+                    .with_note("string literals have no escape sequences")
+            }
+            DomainError::InvalidSourceMap(message) => {
+                Diagnostic::unlocated(format!("invalid source map: {}", message)).with_code("E0302")
+            }
+        }
+    }
+}
+
+fn describe(token_type: &TokenType) -> &'static str {
+    match token_type {
+        TokenType::Null => "whitespace",
+        TokenType::SemiColon => "`;`",
+        TokenType::Number => "a number",
+        TokenType::String => "a string",
+    }
+}
+
+fn describe_all(token_types: &[TokenType]) -> String {
+    match token_types {
+        [] => "nothing".to_owned(),
+        [token_type] => describe(token_type).to_owned(),
+        [init @ .., last] => format!(
+            "{} or {}",
+            init.iter().map(describe).collect::<Vec<_>>().join(", "),
+            describe(last)
+        ),
+    }
+}
+
+fn kind_names() -> String {
+    let names: Vec<String> = TokenType::KINDS
+        .iter()
+        .map(|kind| format!("{:?}", kind))
+        .collect();
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, init)) => format!("{} and {}", init.join(", "), last),
+        None => String::new(),
+    }
+}
+
+fn help_for(expected: &[TokenType]) -> &'static str {
+    if expected == [TokenType::SemiColon] {
+        "statements end with `;`"
+    } else {
+        "statements are number or string literals followed by `;`"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Style {
+    // No escape codes, for logs and files.
+    #[default]
+    Plain,
+    // ANSI colors, for terminals.
+    Color,
+}
+
+struct Palette {
+    level: &'static str,
+    secondary: &'static str,
+    emphasis: &'static str,
+    reset: &'static str,
+}
+
+impl Palette {
+    fn new(style: Style, level: Level) -> Palette {
+        match style {
+            Style::Plain => Palette {
+                level: "",
+                secondary: "",
+                emphasis: "",
+                reset: "",
+            },
+            Style::Color => Palette {
+                level: match level {
+                    Level::Error => "\x1b[1;31m",
+                    Level::Warning => "\x1b[1;33m",
+                },
+                secondary: "\x1b[1;34m",
+                emphasis: "\x1b[1m",
+                reset: "\x1b[0m",
+            },
+        }
+    }
+}
+
+// Renders `diagnostic` the way rustc does: a header, the location, the
+// source lines involved with the primary span underlined by `^` and related
+// spans by `-`, then notes and help.
+pub fn render(diagnostic: &Diagnostic, file: &str, source: &str, style: Style) -> String {
+    let palette = Palette::new(style, diagnostic.level);
+    let mut output = format!(
+        "{}{}",
+        palette.level,
+        match diagnostic.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        }
+    );
+    if let Some(code) = diagnostic.code {
+        output.push_str(&format!("[{}]", code));
+    }
+    output.push_str(&format!(
+        "{}: {}{}{}\n",
+        palette.reset, palette.emphasis, diagnostic.message, palette.reset
+    ));
+
+    let mut annotations = Vec::new();
+    if let Some(span) = diagnostic.span {
+        annotations.push((span, diagnostic.label.as_deref(), true));
+    }
+    for label in diagnostic.related.iter() {
+        annotations.push((label.span, Some(label.message.as_str()), false));
+    }
+    let lines = Lines::new(source);
+    let mut shown: Vec<usize> = annotations
+        .iter()
+        .map(|(span, _, _)| lines.line_of(span.start))
+        .collect();
+    shown.sort_unstable();
+    shown.dedup();
+    let width = shown.last().map_or(0, |line| (line + 1).to_string().len());
+    let gutter = format!(
+        "{}{} |{}",
+        palette.secondary,
+        " ".repeat(width),
+        palette.reset
+    );
+
+    if let Some(span) = diagnostic.span {
+        let line = lines.line_of(span.start);
+        let column = source[lines.start(line)..span.start].chars().count();
+        output.push_str(&format!(
+            "{}{}-->{} {}:{}:{}\n",
+            " ".repeat(width),
+            palette.secondary,
+            palette.reset,
+            file,
+            line + 1,
+            column + 1
+        ));
+    }
+    if !shown.is_empty() {
+        output.push_str(&format!("{}\n", gutter));
+    }
+    for (index, line) in shown.iter().enumerate() {
+        if index > 0 && shown[index - 1] + 1 < *line {
+            output.push_str(&format!("{}...{}\n", palette.secondary, palette.reset));
+        }
+        let text = lines.text(*line);
+        output.push_str(&format!(
+            "{}{:>width$} |{} {}\n",
+            palette.secondary,
+            line + 1,
+            palette.reset,
+            text,
+            width = width
+        ));
+        let mut on_line: Vec<_> = annotations
+            .iter()
+            .filter(|(span, _, _)| lines.line_of(span.start) == *line)
+            .collect();
+        on_line.sort_by_key(|(span, _, primary)| (!primary, span.start));
+        for (span, label, primary) in on_line {
+            let line_start = lines.start(*line);
+            let end = line_start + text.len();
+            // A span may start past the text, on a stripped `\r`.
+            let start = span.start.min(end);
+            let padding = source[line_start..start].chars().count();
+            let underlined = source[start..span.end.clamp(start, end)]
+                .chars()
+                .count()
+                .max(1);
+            let (mark, color) = if *primary {
+                ("^", palette.level)
+            } else {
+                ("-", palette.secondary)
+            };
+            let label = label.map_or(String::new(), |label| format!(" {}", label));
+            output.push_str(&format!(
+                "{} {}{}{}{}{}\n",
+                gutter,
+                " ".repeat(padding),
+                color,
+                mark.repeat(underlined),
+                label,
+                palette.reset
+            ));
+        }
+    }
+    let mut trailers: Vec<(&str, &str)> = diagnostic
+        .notes
+        .iter()
+        .map(|note| ("note", note.as_str()))
+        .collect();
+    if let Some(help) = &diagnostic.help {
+        trailers.push(("help", help));
+    }
+    if !trailers.is_empty() && !shown.is_empty() {
+        output.push_str(&format!("{}\n", gutter));
+    }
+    for (kind, text) in trailers {
+        let prefix = if shown.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "{}{} ={} ",
+                palette.secondary,
+                " ".repeat(width),
+                palette.reset
+            )
+        };
+        output.push_str(&format!(
+            "{}{}{}:{} {}\n",
+            prefix, palette.emphasis, kind, palette.reset, text
+        ));
+    }
+    output
+}
+
+// Line starts of a text, lines end at `\n` which is not part of them.
+struct Lines<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl Lines<'_> {
+    fn new(source: &str) -> Lines<'_> {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Lines { source, starts }
+    }
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|start| *start <= offset) - 1
+    }
+    fn start(&self, line: usize) -> usize {
+        self.starts[line]
+    }
+    fn text(&self, line: usize) -> &str {
+        let end = self
+            .starts
+            .get(line + 1)
+            .map_or(self.source.len(), |next| next - 1);
+        self.source[self.starts[line]..end].trim_end_matches('\r')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{early_errors::check, parser_factory::ParserFactory};
+
+    fn parse_error(source: &str) -> Diagnostic {
+        ParserFactory::create(source.to_owned())
+            .parse()
+            .unwrap_err()
+            .to_diagnostic()
+    }
+
+    #[test]
+    fn test_unexpected_token() {
+        assert_eq!(
+            render(
+                &parse_error("1;\n2 'a';"),
+                "in.js",
+                "1;\n2 'a';",
+                Style::Plain
+            ),
+            "\
+error[E0002]: expected `;`, found a string
+ --> in.js:2:3
+  |
+2 | 2 'a';
+  |   ^^^ expected `;`
+  |
+  = help: statements end with `;`
+"
+        );
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        assert_eq!(
+            render(&parse_error("42"), "in.js", "42", Style::Plain),
+            "\
+error[E0003]: unexpected end of input, expected `;`
+ --> in.js:1:3
+  |
+1 | 42
+  |   ^ expected `;`
+  |
+  = help: statements end with `;`
+"
+        );
+    }
+
+    #[test]
+    fn test_end_of_input_after_carriage_return() {
+        assert_eq!(
+            render(&parse_error("42\r"), "in.js", "42\r", Style::Plain),
+            "\
+error[E0003]: unexpected end of input, expected `;`
+ --> in.js:1:4
+  |
+1 | 42
+  |   ^ expected `;`
+  |
+  = help: statements end with `;`
+"
+        );
+    }
+
+    #[test]
+    fn test_number_too_large() {
+        let source = format!("1; {};", "9".repeat(40));
+        assert_eq!(
+            render(&parse_error(&source), "in.js", &source, Style::Plain),
+            "\
+error[E0005]: number literal is too large
+ --> in.js:1:4
+  |
+1 | 1; 9999999999999999999999999999999999999999;
+  |    ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ does not fit in 128 bits
+  |
+  = note: numbers are stored as 128-bit unsigned integers
+"
+        );
+    }
+
+    #[test]
+    fn test_related_labels_and_skipped_lines() {
+        let source = "'use strict';\n1;\n2;\n017;";
+        let program = ParserFactory::create(source.to_owned()).parse().unwrap();
+        let diagnostic = check(&program, source).remove(0);
+        assert_eq!(
+            render(&diagnostic, "in.js", source, Style::Plain),
+            "\
+error[E0400]: octal literals are not allowed in strict mode
+ --> in.js:4:1
+  |
+1 | 'use strict';
+  | ------------- strict mode enabled here
+...
+4 | 017;
+  | ^^^ not allowed in strict mode
+  |
+  = help: write the value in decimal
+"
+        );
+    }
+
+    #[test]
+    fn test_unlocated() {
+        let diagnostic = DomainError::UnknownTokenKind("Letter".to_owned()).to_diagnostic();
+        assert_eq!(
+            render(&diagnostic, "lexer.toml", "", Style::Plain),
+            "\
+error[E0105]: unknown token kind `Letter`
+help: known kinds are SemiColon, Number and String
+"
+        );
+    }
+
+    #[test]
+    fn test_color() {
+        let diagnostic = Diagnostic::new("bad", Span::new(0, 1))
+            .with_level(Level::Warning)
+            .with_label("here");
+        assert_eq!(
+            render(&diagnostic, "in.js", "x", Style::Color),
+            "\x1b[1;33mwarning\x1b[0m: \x1b[1mbad\x1b[0m\n \x1b[1;34m-->\x1b[0m in.js:1:1\n\
+             \x1b[1;34m  |\x1b[0m\n\
+             \x1b[1;34m1 |\x1b[0m x\n\
+             \x1b[1;34m  |\x1b[0m \x1b[1;33m^ here\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_every_error_has_a_code() {
+        let errors = [
+            DomainError::ParseIntError,
+            DomainError::NumberTooLarge {
+                span: Span::default(),
+            },
+            DomainError::ConfigIo("x".to_owned()),
+            DomainError::Io("x".to_owned()),
+            DomainError::InvalidUtf8 { offset: 0 },
+            DomainError::UnexpectedCharacter {
+                character: 'é',
+                offset: 0,
+            },
+        ];
+        for error in errors {
+            assert!(error.to_diagnostic().code.is_some(), "{:?}", error);
+        }
+    }
+}
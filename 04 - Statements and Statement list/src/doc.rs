@@ -0,0 +1,156 @@
+// Document algebra from Wadler's "A prettier printer": a layout is described
+// once and `render` picks, group by group, whether it fits flat on the
+// remaining line or has to break.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Doc {
+    Text(String),
+    // A space when flat, a newline when the enclosing group breaks.
+    Line,
+    // Nothing when flat, a newline when the enclosing group breaks.
+    SoftLine,
+    // Always a newline.
+    HardLine,
+    Concat(Vec<Doc>),
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+    pub fn nest(indent: usize, doc: Doc) -> Doc {
+        Doc::Nest(indent, Box::new(doc))
+    }
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    let mut commands = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = commands.pop() {
+        match doc {
+            Doc::Text(text) => {
+                output.push_str(text);
+                column = match text.rfind('\n') {
+                    Some(index) => text[index + 1..].chars().count(),
+                    None => column + text.chars().count(),
+                };
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if *doc == Doc::Line {
+                    output.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                output.push('\n');
+                output.push_str(&" ".repeat(indent));
+                column = indent;
+            }
+            Doc::Concat(docs) => {
+                commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+            }
+            Doc::Nest(nested, doc) => commands.push((indent + nested, mode, doc)),
+            Doc::Group(doc) => {
+                let mut rest = commands.clone();
+                rest.push((indent, Mode::Flat, doc));
+                let mode = if mode == Mode::Flat || fits(width as isize - column as isize, rest) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                commands.push((indent, mode, doc));
+            }
+        }
+    }
+    output
+}
+
+// Whether the text up to the next line break fits in `remaining` columns.
+fn fits(mut remaining: isize, mut commands: Vec<(usize, Mode, &Doc)>) -> bool {
+    while remaining >= 0 {
+        let Some((indent, mode, doc)) = commands.pop() else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) => match text.find('\n') {
+                Some(index) => return text[..index].chars().count() as isize <= remaining,
+                None => remaining -= text.chars().count() as isize,
+            },
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Concat(docs) => commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+            Doc::Nest(nested, doc) => commands.push((indent + nested, mode, doc)),
+            Doc::Group(doc) => commands.push((indent, mode, doc)),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Doc {
+        let mut docs = vec![Doc::text("[")];
+        let mut body = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                body.push(Doc::text(","));
+                body.push(Doc::Line);
+            } else {
+                body.push(Doc::SoftLine);
+            }
+            body.push(Doc::text(*item));
+        }
+        docs.push(Doc::nest(2, Doc::concat(body)));
+        docs.push(Doc::SoftLine);
+        docs.push(Doc::text("]"));
+        Doc::group(Doc::concat(docs))
+    }
+
+    #[test]
+    fn test_group_fits() {
+        assert_eq!(render(&list(&["1", "2", "3"]), 20), "[1, 2, 3]");
+        assert_eq!(render(&list(&["1", "2", "3"]), 9), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn test_group_breaks() {
+        assert_eq!(render(&list(&["1", "2", "3"]), 8), "[\n  1,\n  2,\n  3\n]");
+    }
+
+    #[test]
+    fn test_text_after_group_counts() {
+        let doc = Doc::concat([list(&["1", "2"]), Doc::text(";")]);
+        assert_eq!(render(&doc, 7), "[1, 2];");
+        assert_eq!(render(&doc, 6), "[\n  1,\n  2\n];");
+    }
+
+    #[test]
+    fn test_hard_line_and_multi_line_text() {
+        let doc = Doc::concat([
+            Doc::text("/* a\n  b */"),
+            Doc::group(Doc::concat([Doc::Line, Doc::text("1;")])),
+            Doc::HardLine,
+            Doc::text("2;"),
+        ]);
+        assert_eq!(render(&doc, 9), "/* a\n  b */ 1;\n2;");
+        assert_eq!(render(&doc, 8), "/* a\n  b */\n1;\n2;");
+    }
+}
@@ -0,0 +1,113 @@
+// Graphviz DOT form of a `Program`, for drawing the tree with
+// `dot -Tsvg`. Each node is labeled with its type, its value for literals,
+// and its span.
+
+use crate::{
+    models::{Program, Span},
+    visit::{NodeRef, Visit, VisitControl},
+};
+
+pub fn to_dot(program: &Program) -> String {
+    let mut printer = Dot {
+        output: String::from("digraph ast {\n    node [shape=box, fontname=\"monospace\"];\n"),
+        count: 0,
+        parents: Vec::new(),
+    };
+    printer.visit_program(program);
+    printer.output.push_str("}\n");
+    printer.output
+}
+
+struct Dot {
+    output: String,
+    // Nodes printed so far, ids are `n0`, `n1`...
+    count: usize,
+    // Ids of the nodes being visited, the innermost last.
+    parents: Vec<usize>,
+}
+
+impl Dot {
+    fn node(&mut self, label: &str, span: Span) {
+        let id = self.count;
+        self.count += 1;
+        self.output.push_str(&format!(
+            "    n{} [label=\"{}\\n{}..{}\"];\n",
+            id,
+            escape(label),
+            span.start,
+            span.end
+        ));
+        if let Some(parent) = self.parents.last() {
+            self.output
+                .push_str(&format!("    n{} -> n{};\n", parent, id));
+        }
+        self.parents.push(id);
+    }
+}
+
+// The `Statement`, `Expression` and `Literal` enums only wrap the node they
+// hold and don't show up.
+impl Visit for Dot {
+    fn enter(&mut self, node: NodeRef<'_>) -> VisitControl {
+        match node {
+            NodeRef::Program(program) => self.node("Program", program.span),
+            NodeRef::ExpressionStatement(statement) => {
+                self.node("ExpressionStatement", statement.span)
+            }
+            NodeRef::NumericLiteral(literal) => {
+                self.node(&format!("NumericLiteral {}", literal.value), literal.span)
+            }
+            NodeRef::StringLiteral(literal) => self.node(
+                &format!(
+                    "StringLiteral {}",
+                    serde_json::to_string(&literal.value).expect("strings are serializable")
+                ),
+                literal.span,
+            ),
+            NodeRef::Statement(_) | NodeRef::Expression(_) | NodeRef::Literal(_) => {}
+        }
+        VisitControl::Continue
+    }
+    fn leave(&mut self, node: NodeRef<'_>) {
+        if !matches!(
+            node,
+            NodeRef::Statement(_) | NodeRef::Expression(_) | NodeRef::Literal(_)
+        ) {
+            self.parents.pop();
+        }
+    }
+}
+
+// Escapes for a double-quoted DOT string.
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_factory::ParserFactory;
+
+    #[test]
+    fn test_to_dot() {
+        let program = ParserFactory::create("42; 'say \"hi\"';".to_owned())
+            .parse()
+            .unwrap();
+        assert_eq!(
+            to_dot(&program),
+            r#"digraph ast {
+    node [shape=box, fontname="monospace"];
+    n0 [label="Program\n0..15"];
+    n1 [label="ExpressionStatement\n0..3"];
+    n0 -> n1;
+    n2 [label="NumericLiteral 42\n0..2"];
+    n1 -> n2;
+    n3 [label="ExpressionStatement\n4..15"];
+    n0 -> n3;
+    n4 [label="StringLiteral \"say \\\"hi\\\"\"\n4..14"];
+    n3 -> n4;
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,156 @@
+// ECMAScript early errors: grammatical programs that are still rejected
+// before running.
+//
+// The only one reachable in this grammar is a numeric literal with a leading
+// zero in strict mode code. Declarations, assignments, labels, functions and
+// `delete` come with their own checks once they are parsed.
+
+use crate::{
+    diagnostics::Diagnostic,
+    models::{Expression, Literal, NumericLiteral, Program, Span, Statement},
+    visit::{walk_numeric_literal, Visit},
+};
+
+// `source` is the parsed text, literal values don't keep their leading
+// zeros.
+pub fn check(program: &Program, source: &str) -> Vec<Diagnostic> {
+    match use_strict_directive(program) {
+        Some(use_strict) => strict_literals(program, source, Some(use_strict)),
+        None => Vec::new(),
+    }
+}
+
+// Like `check`, for code that is strict without a directive, such as a
+// module.
+pub fn check_strict(program: &Program, source: &str) -> Vec<Diagnostic> {
+    strict_literals(program, source, use_strict_directive(program))
+}
+
+// The "use strict" statement of the directive prologue, the leading string
+// statements. Directives have no escapes in this grammar, so the value is the
+// raw text.
+pub fn use_strict_directive(program: &Program) -> Option<Span> {
+    program
+        .body
+        .iter()
+        .map_while(|statement| match statement {
+            Statement::Expression(statement) => match &statement.expression {
+                Expression::Literal(Literal::String(literal)) => {
+                    Some((literal.value.as_str(), statement.span))
+                }
+                _ => None,
+            },
+        })
+        .find(|(directive, _)| *directive == "use strict")
+        .map(|(_, span)| span)
+}
+
+fn strict_literals(program: &Program, source: &str, use_strict: Option<Span>) -> Vec<Diagnostic> {
+    let mut checker = StrictLiterals {
+        source,
+        use_strict,
+        diagnostics: Vec::new(),
+    };
+    checker.visit_program(program);
+    checker.diagnostics
+}
+
+struct StrictLiterals<'a> {
+    source: &'a str,
+    use_strict: Option<Span>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Visit for StrictLiterals<'_> {
+    fn visit_numeric_literal(&mut self, literal: &NumericLiteral) {
+        let raw = &self.source[literal.span.start..literal.span.end];
+        if raw.len() > 1 && raw.starts_with('0') {
+            let (message, help) = if raw.bytes().all(|digit| (b'0'..=b'7').contains(&digit)) {
+                (
+                    "octal literals are not allowed in strict mode",
+                    "write the value in decimal",
+                )
+            } else {
+                (
+                    "decimals with leading zeros are not allowed in strict mode",
+                    "remove the leading zeros",
+                )
+            };
+            let mut diagnostic = Diagnostic::new(message, literal.span)
+                .with_code("E0400")
+                .with_label("not allowed in strict mode")
+                .with_help(help);
+            if let Some(use_strict) = self.use_strict {
+                diagnostic = diagnostic.with_related(use_strict, "strict mode enabled here");
+            }
+            self.diagnostics.push(diagnostic);
+        }
+        walk_numeric_literal(self, literal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_factory::ParserFactory;
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        check(
+            &ParserFactory::create(source.to_owned()).parse().unwrap(),
+            source,
+        )
+    }
+
+    #[test]
+    fn test_leading_zeros_in_strict_mode() {
+        let diagnostics = check_source("'use strict';\n0; 10; 017; 08;");
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "octal literals are not allowed in strict mode",
+                    Some(Span::new(21, 24))
+                ),
+                (
+                    "decimals with leading zeros are not allowed in strict mode",
+                    Some(Span::new(26, 28))
+                ),
+            ]
+        );
+        for diagnostic in diagnostics {
+            assert_eq!(diagnostic.related[0].span, Span::new(0, 13));
+        }
+    }
+
+    #[test]
+    fn test_sloppy_mode() {
+        assert_eq!(check_source("017; 08;"), Vec::new());
+        assert_eq!(check_source("017; 'use strict';"), Vec::new());
+    }
+
+    #[test]
+    fn test_use_strict_directive() {
+        let directive = |source: &str| {
+            use_strict_directive(&ParserFactory::create(source.to_owned()).parse().unwrap())
+        };
+        assert_eq!(directive("'use strict';"), Some(Span::new(0, 13)));
+        assert_eq!(directive("'a'; \"use strict\"; 1;"), Some(Span::new(5, 18)));
+        // Only the prologue counts.
+        assert_eq!(directive("1; 'use strict';"), None);
+        assert_eq!(directive("'use  strict';"), None);
+    }
+
+    #[test]
+    fn test_forced_strict_mode() {
+        let source = "1; 017;";
+        let program = ParserFactory::create(source.to_owned()).parse().unwrap();
+        let diagnostics = check_strict(&program, source);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Some(Span::new(3, 6)));
+        assert!(diagnostics[0].related.is_empty());
+    }
+}
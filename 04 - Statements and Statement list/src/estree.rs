@@ -0,0 +1,194 @@
+use serde_json::{json, Map, Value};
+
+use crate::models::{Expression, Literal, Program, Span, Statement};
+
+// JSON shape used when printing a `Program`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    // The `Serialize` impls of the models, kept for existing consumers.
+    #[default]
+    Legacy,
+    // ESTree nodes with `start`/`end` offsets, as produced by acorn.
+    Estree,
+}
+
+pub fn to_value(program: &Program, format: OutputFormat, source: Option<&str>) -> Value {
+    match format {
+        OutputFormat::Legacy => serde_json::to_value(program).expect("Program is serializable"),
+        OutputFormat::Estree => Estree { source }.program(program),
+    }
+}
+
+// `source` is the parsed text, used for `raw` and `directive`. Without it
+// they are rebuilt from the values.
+struct Estree<'a> {
+    source: Option<&'a str>,
+}
+
+impl Estree<'_> {
+    fn program(&self, program: &Program) -> Value {
+        // The leading string statements form the directive prologue.
+        let prologue = program
+            .body
+            .iter()
+            .take_while(|statement| {
+                matches!(
+                    statement,
+                    Statement::Expression(statement)
+                        if matches!(statement.expression, Expression::Literal(Literal::String(_)))
+                )
+            })
+            .count();
+        let body: Vec<Value> = program
+            .body
+            .iter()
+            .enumerate()
+            .map(|(index, statement)| self.statement(statement, index < prologue))
+            .collect();
+        node(
+            "Program",
+            program.span,
+            json!({ "body": body, "sourceType": "script" }),
+        )
+    }
+    fn statement(&self, statement: &Statement, directive: bool) -> Value {
+        match statement {
+            Statement::Expression(statement) => {
+                let mut fields = json!({ "expression": self.expression(&statement.expression) });
+                if directive {
+                    let raw = self.raw(&statement.expression);
+                    fields["directive"] = json!(raw[1..raw.len() - 1]);
+                }
+                node("ExpressionStatement", statement.span, fields)
+            }
+        }
+    }
+    fn expression(&self, expression: &Expression) -> Value {
+        let value = match expression {
+            Expression::Literal(Literal::Numeric(literal)) => match u64::try_from(literal.value) {
+                Ok(value) => json!(value),
+                Err(_) => json!(literal.value as f64),
+            },
+            Expression::Literal(Literal::String(literal)) => json!(literal.value),
+        };
+        node(
+            "Literal",
+            expression.span(),
+            json!({ "value": value, "raw": self.raw(expression) }),
+        )
+    }
+    fn raw(&self, expression: &Expression) -> String {
+        if let Some(raw) = self.source_raw(expression) {
+            return raw.to_owned();
+        }
+        match expression {
+            Expression::Literal(Literal::Numeric(literal)) => literal.value.to_string(),
+            Expression::Literal(Literal::String(literal)) if literal.value.contains('"') => {
+                format!("'{}'", literal.value)
+            }
+            Expression::Literal(Literal::String(literal)) => format!("\"{}\"", literal.value),
+        }
+    }
+    // The text under the span, when it reads as the literal. Spans of a
+    // program read back from JSON or built by hand don't point into `source`.
+    fn source_raw(&self, expression: &Expression) -> Option<&str> {
+        let span = expression.span();
+        let raw = self.source?.get(span.start..span.end)?;
+        let matches = match expression {
+            Expression::Literal(Literal::Numeric(literal)) => {
+                raw.parse::<u128>() == Ok(literal.value)
+            }
+            Expression::Literal(Literal::String(_)) => {
+                raw.len() >= 2
+                    && (raw.starts_with('"') && raw.ends_with('"')
+                        || raw.starts_with('\'') && raw.ends_with('\''))
+            }
+        };
+        matches.then_some(raw)
+    }
+}
+
+fn node(node_type: &str, span: Span, fields: Value) -> Value {
+    let mut node = Map::new();
+    node.insert("type".to_owned(), json!(node_type));
+    node.insert("start".to_owned(), json!(span.start));
+    node.insert("end".to_owned(), json!(span.end));
+    if let Value::Object(fields) = fields {
+        node.extend(fields);
+    }
+    Value::Object(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::parser_factory::ParserFactory;
+
+    #[test]
+    fn test_fixtures() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/estree");
+        let mut checked = 0;
+        for entry in fs::read_dir(fixtures).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("js") {
+                continue;
+            }
+            let source = fs::read_to_string(&path).unwrap();
+            let expected: Value =
+                serde_json::from_str(&fs::read_to_string(path.with_extension("json")).unwrap())
+                    .unwrap();
+            let program = ParserFactory::create(source.clone()).parse().unwrap();
+            assert_eq!(
+                to_value(&program, OutputFormat::Estree, Some(&source)),
+                expected,
+                "{}",
+                path.display()
+            );
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn test_without_source() {
+        let program = ParserFactory::create("'say \"hi\"'; 7;".to_owned())
+            .parse()
+            .unwrap();
+        let value = to_value(&program, OutputFormat::Estree, None);
+        assert_eq!(value["body"][0]["expression"]["raw"], json!("'say \"hi\"'"));
+        assert_eq!(value["body"][0]["directive"], json!("say \"hi\""));
+        assert_eq!(value["body"][1]["expression"]["raw"], json!("7"));
+        assert_eq!(value["body"][1].get("directive"), None);
+    }
+
+    #[test]
+    fn test_spans_outside_the_source() {
+        let source = "'use strict'; 42;";
+        let parsed = ParserFactory::create(source.to_owned()).parse().unwrap();
+        // Read back from JSON, every span is empty.
+        let program: Program =
+            serde_json::from_value(to_value(&parsed, OutputFormat::Legacy, None)).unwrap();
+        let value = to_value(&program, OutputFormat::Estree, Some(source));
+        assert_eq!(
+            value["body"][0]["expression"]["raw"],
+            json!("\"use strict\"")
+        );
+        assert_eq!(value["body"][0]["directive"], json!("use strict"));
+        assert_eq!(value["body"][1]["expression"]["raw"], json!("42"));
+        // Spans of another source, the string's end cuts `é` in two.
+        let value = to_value(&parsed, OutputFormat::Estree, Some("'use striéé'; 42;"));
+        assert_eq!(value["body"][0]["directive"], json!("use strict"));
+        assert_eq!(value["body"][1]["expression"]["raw"], json!("42"));
+    }
+
+    #[test]
+    fn test_legacy_format() {
+        let program = ParserFactory::create("42;".to_owned()).parse().unwrap();
+        assert_eq!(
+            to_value(&program, OutputFormat::Legacy, None),
+            serde_json::to_value(&program).unwrap()
+        );
+    }
+}
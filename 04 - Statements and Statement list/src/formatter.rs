@@ -0,0 +1,246 @@
+use crate::{
+    codegen::{quote, QuoteStyle},
+    doc::{render, Doc},
+    models::{DomainError, Expression, Literal, Program, Statement},
+    parser_factory::ParserFactory,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub width: usize,
+    pub quote: QuoteStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            width: 80,
+            quote: QuoteStyle::default(),
+        }
+    }
+}
+
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, DomainError> {
+    let program = ParserFactory::create(source.to_owned()).parse()?;
+    format_program(&program, source, options)
+}
+
+// Lays out `program` one statement per line. `source` is the text it was
+// parsed from, comments and blank lines are recovered from the gaps between
+// statements since the tokenizer drops them.
+//
+// A comment starting on the line where a statement ends stays after it, as
+// long as the line fits in `width`. Other comments keep their line, and runs
+// of blank lines collapse to one.
+pub fn format_program(
+    program: &Program,
+    source: &str,
+    options: &FormatOptions,
+) -> Result<String, DomainError> {
+    let mut docs = Vec::new();
+    let expressions: Vec<&Expression> = program
+        .body
+        .iter()
+        .map(|statement| match statement {
+            Statement::Expression(statement) => &statement.expression,
+        })
+        .collect();
+    let first = expressions.first().map_or(source.len(), |e| e.span().start);
+    let (mut pending, mut newlines) = trivia(&source[..first]);
+    for (index, expression) in expressions.iter().enumerate() {
+        for comment in pending.iter() {
+            separate(&mut docs, comment.newlines_before);
+            docs.push(Doc::text(comment.text));
+        }
+        // Statements always start on their own line.
+        let after_comment = !pending.is_empty();
+        separate(
+            &mut docs,
+            if after_comment {
+                newlines
+            } else {
+                newlines.max(1)
+            },
+        );
+
+        let next = expressions
+            .get(index + 1)
+            .map_or(source.len(), |next| next.span().start);
+        let (comments, after) = trivia(&source[expression.span().end..next]);
+        let trailing = comments
+            .iter()
+            .take_while(|comment| comment.newlines_before == 0)
+            .count();
+        let mut statement = vec![Doc::text(format!(
+            "{};",
+            print_expression(expression, options)?
+        ))];
+        for comment in comments[..trailing].iter() {
+            statement.push(Doc::Line);
+            statement.push(Doc::text(comment.text));
+        }
+        docs.push(Doc::group(Doc::concat(statement)));
+        pending = comments[trailing..].to_vec();
+        newlines = after;
+    }
+    for comment in pending.iter() {
+        separate(&mut docs, comment.newlines_before);
+        docs.push(Doc::text(comment.text));
+    }
+    docs.push(Doc::HardLine);
+    Ok(render(&Doc::concat(docs), options.width))
+}
+
+fn print_expression(
+    expression: &Expression,
+    options: &FormatOptions,
+) -> Result<String, DomainError> {
+    match expression {
+        Expression::Literal(Literal::Numeric(literal)) => Ok(literal.value.to_string()),
+        Expression::Literal(Literal::String(literal)) => quote(&literal.value, options.quote),
+    }
+}
+
+// Line break before the next item, none at the start of the file.
+fn separate(docs: &mut Vec<Doc>, newlines: usize) {
+    if docs.is_empty() {
+        return;
+    }
+    match newlines {
+        0 => docs.push(Doc::text(" ")),
+        1 => docs.push(Doc::HardLine),
+        _ => docs.extend([Doc::HardLine, Doc::HardLine]),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Comment<'a> {
+    text: &'a str,
+    // Line breaks between the previous item and this comment.
+    newlines_before: usize,
+}
+
+// Comments in text skipped by the bundled lexer, along with the line breaks
+// left after the last one. Semicolons closing a statement are ignored.
+fn trivia(text: &str) -> (Vec<Comment<'_>>, usize) {
+    let mut comments = Vec::new();
+    let mut newlines = 0;
+    let mut rest = text;
+    while let Some(character) = rest.chars().next() {
+        let length = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            comment.find("*/").map_or(rest.len(), |index| index + 4)
+        } else {
+            if character == '\n' {
+                newlines += 1;
+            }
+            rest = &rest[character.len_utf8()..];
+            continue;
+        };
+        comments.push(Comment {
+            text: &rest[..length],
+            newlines_before: newlines,
+        });
+        newlines = 0;
+        rest = &rest[length..];
+    }
+    (comments, newlines)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use regex::Regex;
+
+    use super::*;
+
+    fn corpus() -> Vec<String> {
+        let mut corpus: Vec<String> = [
+            "42;",
+            "\n\n  1;2;;;   'a'\n;\n\n\n\"b\";\n\n",
+            "// header\n\n/* a */ /* b */ 1; // one\n2 /* two */;\n\n\n// end\n",
+            "1; /* multi\n * line */ 2;\n/* own\n line */\n\n3;",
+            "'say \"hi\"'; \"it's\"; 'tail'; /* x */",
+            "// only a comment before\n7;",
+        ]
+        .iter()
+        .map(|source| source.to_string())
+        .collect();
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/estree");
+        for entry in fs::read_dir(fixtures).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) == Some("js") {
+                corpus.push(fs::read_to_string(path).unwrap());
+            }
+        }
+        corpus
+    }
+
+    // Comments in order, strings are matched so their content is skipped.
+    fn comments(source: &str) -> Vec<&str> {
+        Regex::new(r#"//.*|/\*[\s\S]*?\*/|"[^"]*"|'[^']*'"#)
+            .unwrap()
+            .find_iter(source)
+            .map(|found| found.as_str())
+            .filter(|found| found.starts_with('/'))
+            .collect()
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(
+            format(
+                "\n\n  1;2;;;   'a'\n;\n\n\n\"b\";\n\n",
+                &FormatOptions::default()
+            )
+            .unwrap(),
+            "1;\n2;\n\"a\";\n\n\"b\";\n"
+        );
+        assert_eq!(
+            format(
+                "// header\n\n/* a */ /* b */ 1; // one\n2 /* two */;\n\n\n// end\n",
+                &FormatOptions::default()
+            )
+            .unwrap(),
+            "// header\n\n/* a */ /* b */ 1; // one\n2; /* two */\n\n// end\n"
+        );
+    }
+
+    #[test]
+    fn test_trailing_comments_follow_width() {
+        let source = "1; /* a */ /* b */\n2;";
+        let mut options = FormatOptions {
+            width: 18,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format(source, &options).unwrap(),
+            "1; /* a */ /* b */\n2;\n"
+        );
+        options.width = 17;
+        let narrow = format(source, &options).unwrap();
+        assert_eq!(narrow, "1;\n/* a */\n/* b */\n2;\n");
+        assert_eq!(format(&narrow, &options).unwrap(), narrow);
+    }
+
+    #[test]
+    fn test_idempotent_and_keeps_comments() {
+        for width in [80, 10, 1] {
+            for quote in [QuoteStyle::Double, QuoteStyle::Single] {
+                let options = FormatOptions { width, quote };
+                for source in corpus() {
+                    let formatted = format(&source, &options).unwrap();
+                    assert_eq!(
+                        format(&formatted, &options).unwrap(),
+                        formatted,
+                        "{:?}",
+                        source
+                    );
+                    assert_eq!(comments(&formatted), comments(&source), "{:?}", source);
+                }
+            }
+        }
+    }
+}
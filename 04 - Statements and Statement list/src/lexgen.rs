@@ -0,0 +1,853 @@
+// Compiles a token spec into a minimized DFA over bytes.
+//
+// Only std is used so `build.rs` can include this file and emit the tables
+// ahead of time. The supported regex syntax is what the lexer specs use:
+// literals, `.`, classes, `\s \S \d \D \w \W`, groups, `|`, `* + ?` and their
+// lazy forms, and a leading `^`. Class members are ASCII and `\d \w` only
+// match ASCII characters, unlike with the `regex` crate; `\s` matches the
+// Unicode whitespace as `regex` does. Non-ASCII characters are matched as
+// whole UTF-8 sequences, so a match never ends inside a character.
+//
+// Rules are tried together: the longest match wins and earlier rules win
+// ties, like `MatchPolicy::LongestMatch`. A rule with a lazy quantifier stops
+// at its first match, which is what the regex means for patterns such as
+// `^\/\*[\s\S]*?\*\/`.
+//
+// Within a rule the longest match also wins, while the `regex` crate takes
+// the first branch of an alternation that matches: `^a|^ab` matches `ab`
+// here but only `a` with `regex`. The two agree when no branch can match a
+// prefix of what another branch matches, as in the bundled spec.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+pub const DEAD: u16 = 0;
+pub const START: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexGenError {
+    Syntax { rule: usize, message: String },
+    Unsupported { rule: usize, message: String },
+    // The tables number states with u16, `limit` is how many fit.
+    TooManyStates { limit: usize },
+}
+
+// Problems found while generating, reported but not fatal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    // Both rules match `example` with the same length, `winner` comes first.
+    Overlap {
+        winner: usize,
+        loser: usize,
+        example: String,
+    },
+    // The rule never produces a token.
+    Unreachable {
+        rule: usize,
+    },
+    // The rule accepts the empty string, such matches are ignored.
+    MatchesEmpty {
+        rule: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dfa {
+    // Byte to equivalence class.
+    pub classes: [u8; 256],
+    pub class_count: usize,
+    // `transitions[state * class_count + class]`, state 0 is the dead state.
+    pub transitions: Vec<u16>,
+    // Rule accepted in each state.
+    pub accept: Vec<Option<usize>>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Dfa {
+    pub fn state_count(&self) -> usize {
+        self.accept.len()
+    }
+    // Returns the length of the longest non-empty match at the start of
+    // `input` and the rule that produced it.
+    pub fn find(&self, input: &[u8]) -> Option<(usize, usize)> {
+        let mut state = START;
+        let mut found = None;
+        for (index, byte) in input.iter().enumerate() {
+            let class = self.classes[*byte as usize] as usize;
+            state = self.transitions[state as usize * self.class_count + class];
+            if state == DEAD {
+                break;
+            }
+            if let Some(rule) = self.accept[state as usize] {
+                found = Some((index + 1, rule));
+            }
+        }
+        found
+    }
+}
+
+pub fn generate(patterns: &[&str]) -> Result<Dfa, LexGenError> {
+    let mut nfa = Nfa::default();
+    let start = nfa.add_state(usize::MAX);
+    let mut lazy = vec![];
+    for (rule, pattern) in patterns.iter().enumerate() {
+        let mut parser = RegexParser {
+            chars: pattern.chars().collect(),
+            position: 0,
+            rule,
+            lazy: false,
+        };
+        let node = parser.parse()?;
+        lazy.push(parser.lazy);
+        let (rule_start, rule_end) = nfa.build(&node, rule);
+        nfa.states[start].epsilon.push(rule_start);
+        nfa.states[rule_end].accept = Some(rule);
+    }
+    let dfa = determinize(&nfa, start, &lazy)?;
+    let dfa = minimize(dfa);
+    Ok(compress(dfa))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    fn empty() -> ByteSet {
+        ByteSet([0; 4])
+    }
+    fn byte(byte: u8) -> ByteSet {
+        let mut set = ByteSet::empty();
+        set.insert(byte);
+        set
+    }
+    fn range(from: u8, to: u8) -> ByteSet {
+        let mut set = ByteSet::empty();
+        for byte in from..=to {
+            set.insert(byte);
+        }
+        set
+    }
+    fn insert(&mut self, byte: u8) {
+        self.0[(byte / 64) as usize] |= 1 << (byte % 64);
+    }
+    fn contains(&self, byte: u8) -> bool {
+        self.0[(byte / 64) as usize] & (1 << (byte % 64)) != 0
+    }
+    fn union(&self, other: &ByteSet) -> ByteSet {
+        let mut set = *self;
+        for (word, other) in set.0.iter_mut().zip(other.0.iter()) {
+            *word |= other;
+        }
+        set
+    }
+}
+
+// A set of characters: the ASCII ones byte by byte, and the listed
+// non-ASCII ones, or all but the listed ones when `negated`.
+#[derive(Clone)]
+struct CharSet {
+    ascii: ByteSet,
+    others: BTreeSet<char>,
+    negated: bool,
+}
+
+impl CharSet {
+    fn ascii(ascii: ByteSet) -> CharSet {
+        CharSet {
+            ascii,
+            others: BTreeSet::new(),
+            negated: false,
+        }
+    }
+    fn union(&self, other: &CharSet) -> CharSet {
+        let (others, negated) = match (self.negated, other.negated) {
+            (false, false) => (&self.others | &other.others, false),
+            (true, false) => (&self.others - &other.others, true),
+            (false, true) => (&other.others - &self.others, true),
+            (true, true) => (&self.others & &other.others, true),
+        };
+        CharSet {
+            ascii: self.ascii.union(&other.ascii),
+            others,
+            negated,
+        }
+    }
+    fn negate(&self) -> CharSet {
+        let mut ascii = ByteSet::empty();
+        for byte in 0..0x80 {
+            if !self.ascii.contains(byte) {
+                ascii.insert(byte);
+            }
+        }
+        CharSet {
+            ascii,
+            others: self.others.clone(),
+            negated: !self.negated,
+        }
+    }
+    fn into_node(self) -> Node {
+        let mut branches = vec![Node::Set(self.ascii)];
+        if self.negated {
+            // The input is valid UTF-8, so a leading byte and the
+            // continuation bytes it announces match one character.
+            let excluded: Vec<Vec<u8>> = self
+                .others
+                .iter()
+                .map(|character| character.to_string().into_bytes())
+                .collect();
+            for (lead, length) in [(0xc2, 2), (0xe0, 3), (0xf0, 4)] {
+                let excluded: Vec<&[u8]> = excluded
+                    .iter()
+                    .filter(|bytes| bytes.len() == length)
+                    .map(Vec::as_slice)
+                    .collect();
+                let lead = ByteSet::range(lead, [0xdf, 0xef, 0xf4][length - 2]);
+                branches.push(sequences(lead, length, &excluded));
+            }
+        } else {
+            branches.extend(self.others.iter().map(|character| literal(*character)));
+        }
+        if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alternation(branches)
+        }
+    }
+}
+
+// Byte sequences of `length` starting with a byte of `first`, followed by
+// continuation bytes, except the `excluded` ones.
+fn sequences(first: ByteSet, length: usize, excluded: &[&[u8]]) -> Node {
+    let continuation = ByteSet::range(0x80, 0xbf);
+    let mut free = ByteSet::empty();
+    let mut branches = vec![];
+    for byte in 0..=255u8 {
+        if !first.contains(byte) {
+            continue;
+        }
+        let tails: Vec<&[u8]> = excluded
+            .iter()
+            .filter(|bytes| bytes[0] == byte)
+            .map(|bytes| &bytes[1..])
+            .collect();
+        if tails.is_empty() {
+            free.insert(byte);
+        } else if length > 1 {
+            branches.push(Node::Concat(vec![
+                Node::Set(ByteSet::byte(byte)),
+                sequences(continuation, length - 1, &tails),
+            ]));
+        }
+    }
+    let mut items = vec![Node::Set(free)];
+    items.extend((1..length).map(|_| Node::Set(continuation)));
+    branches.push(Node::Concat(items));
+    Node::Alternation(branches)
+}
+
+fn literal(character: char) -> Node {
+    Node::Concat(
+        character
+            .to_string()
+            .bytes()
+            .map(|byte| Node::Set(ByteSet::byte(byte)))
+            .collect(),
+    )
+}
+
+enum Node {
+    Set(ByteSet),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Star(Box<Node>),
+    Plus(Box<Node>),
+    Optional(Box<Node>),
+}
+
+struct RegexParser {
+    chars: Vec<char>,
+    position: usize,
+    rule: usize,
+    lazy: bool,
+}
+
+impl RegexParser {
+    fn parse(&mut self) -> Result<Node, LexGenError> {
+        let node = self.alternation()?;
+        if self.position < self.chars.len() {
+            return Err(self.syntax("unmatched `)`"));
+        }
+        Ok(node)
+    }
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+    fn next(&mut self) -> Option<char> {
+        let next = self.peek();
+        self.position += 1;
+        next
+    }
+    fn syntax(&self, message: &str) -> LexGenError {
+        LexGenError::Syntax {
+            rule: self.rule,
+            message: format!("{} at {}", message, self.position),
+        }
+    }
+    fn unsupported(&self, message: &str) -> LexGenError {
+        LexGenError::Unsupported {
+            rule: self.rule,
+            message: format!("{} at {}", message, self.position),
+        }
+    }
+    fn alternation(&mut self) -> Result<Node, LexGenError> {
+        let mut branches = vec![self.concat()?];
+        while self.peek() == Some('|') {
+            self.next();
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alternation(branches)
+        })
+    }
+    fn concat(&mut self) -> Result<Node, LexGenError> {
+        // Every match starts at the cursor, so a leading anchor changes nothing.
+        if self.peek() == Some('^') {
+            self.next();
+        }
+        let mut items = vec![];
+        while let Some(next) = self.peek() {
+            if next == '|' || next == ')' {
+                break;
+            }
+            items.push(self.repeat()?);
+        }
+        Ok(Node::Concat(items))
+    }
+    fn repeat(&mut self) -> Result<Node, LexGenError> {
+        let mut node = self.atom()?;
+        while let Some(next) = self.peek() {
+            node = match next {
+                '*' => Node::Star(Box::new(node)),
+                '+' => Node::Plus(Box::new(node)),
+                '?' => Node::Optional(Box::new(node)),
+                '{' => return Err(self.unsupported("counted repetition")),
+                _ => break,
+            };
+            self.next();
+            if self.peek() == Some('?') {
+                self.next();
+                self.lazy = true;
+            }
+        }
+        Ok(node)
+    }
+    fn atom(&mut self) -> Result<Node, LexGenError> {
+        match self.next() {
+            Some('(') => {
+                if self.peek() == Some('?') {
+                    self.next();
+                    if self.next() != Some(':') {
+                        return Err(self.unsupported("group flags"));
+                    }
+                }
+                let node = self.alternation()?;
+                if self.next() != Some(')') {
+                    return Err(self.syntax("unclosed group"));
+                }
+                Ok(node)
+            }
+            Some('[') => Ok(self.class()?.into_node()),
+            Some('.') => Ok(CharSet::ascii(ByteSet::byte(b'\n')).negate().into_node()),
+            Some('\\') => Ok(self.escape()?.into_node()),
+            Some('^') => Err(self.unsupported("anchor after the start")),
+            Some('$') => Err(self.unsupported("end anchor")),
+            Some(character) => Ok(literal(character)),
+            None => Err(self.syntax("unexpected end")),
+        }
+    }
+    fn escape(&mut self) -> Result<CharSet, LexGenError> {
+        // Unicode's White_Space, as `regex` matches it.
+        let whitespace = CharSet {
+            others: ['\u{85}', '\u{a0}', '\u{1680}', '\u{2028}', '\u{2029}']
+                .into_iter()
+                .chain('\u{2000}'..='\u{200a}')
+                .chain(['\u{202f}', '\u{205f}', '\u{3000}'])
+                .collect(),
+            ..CharSet::ascii(
+                [b'\t', b'\n', 0x0b, 0x0c, b'\r', b' ']
+                    .iter()
+                    .fold(ByteSet::empty(), |set, byte| {
+                        set.union(&ByteSet::byte(*byte))
+                    }),
+            )
+        };
+        let digits = CharSet::ascii(ByteSet::range(b'0', b'9'));
+        let word = CharSet::ascii(
+            ByteSet::range(b'0', b'9')
+                .union(&ByteSet::range(b'a', b'z'))
+                .union(&ByteSet::range(b'A', b'Z'))
+                .union(&ByteSet::byte(b'_')),
+        );
+        match self.next() {
+            Some('s') => Ok(whitespace),
+            Some('S') => Ok(whitespace.negate()),
+            Some('d') => Ok(digits),
+            Some('D') => Ok(digits.negate()),
+            Some('w') => Ok(word),
+            Some('W') => Ok(word.negate()),
+            Some('n') => Ok(CharSet::ascii(ByteSet::byte(b'\n'))),
+            Some('t') => Ok(CharSet::ascii(ByteSet::byte(b'\t'))),
+            Some('r') => Ok(CharSet::ascii(ByteSet::byte(b'\r'))),
+            Some(escaped) if escaped.is_ascii_punctuation() => {
+                Ok(CharSet::ascii(ByteSet::byte(escaped as u8)))
+            }
+            Some(_) => Err(self.unsupported("escape")),
+            None => Err(self.syntax("unexpected end")),
+        }
+    }
+    fn class(&mut self) -> Result<CharSet, LexGenError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.next();
+        }
+        let mut set = CharSet::ascii(ByteSet::empty());
+        let mut first = true;
+        loop {
+            let member = match self.next() {
+                Some(']') if !first => break,
+                Some('\\') => self.escape()?,
+                Some(from) if from.is_ascii() => {
+                    if self.peek() == Some('-') && self.chars.get(self.position + 1) != Some(&']') {
+                        self.next();
+                        match self.next() {
+                            Some(to) if to.is_ascii() && to >= from => {
+                                CharSet::ascii(ByteSet::range(from as u8, to as u8))
+                            }
+                            _ => return Err(self.syntax("invalid class range")),
+                        }
+                    } else {
+                        CharSet::ascii(ByteSet::byte(from as u8))
+                    }
+                }
+                Some(_) => return Err(self.unsupported("non-ASCII class member")),
+                None => return Err(self.syntax("unclosed class")),
+            };
+            set = set.union(&member);
+            first = false;
+        }
+        Ok(if negated { set.negate() } else { set })
+    }
+}
+
+struct NfaState {
+    // Rule the state belongs to, `usize::MAX` for the shared start state.
+    rule: usize,
+    epsilon: Vec<usize>,
+    edges: Vec<(ByteSet, usize)>,
+    accept: Option<usize>,
+}
+
+#[derive(Default)]
+struct Nfa {
+    states: Vec<NfaState>,
+}
+
+impl Nfa {
+    fn add_state(&mut self, rule: usize) -> usize {
+        self.states.push(NfaState {
+            rule,
+            epsilon: vec![],
+            edges: vec![],
+            accept: None,
+        });
+        self.states.len() - 1
+    }
+    // Thompson construction, returns the start and end states of `node`.
+    fn build(&mut self, node: &Node, rule: usize) -> (usize, usize) {
+        let start = self.add_state(rule);
+        let end = self.add_state(rule);
+        match node {
+            Node::Set(set) => self.states[start].edges.push((*set, end)),
+            Node::Concat(items) => {
+                let mut current = start;
+                for item in items {
+                    let (item_start, item_end) = self.build(item, rule);
+                    self.states[current].epsilon.push(item_start);
+                    current = item_end;
+                }
+                self.states[current].epsilon.push(end);
+            }
+            Node::Alternation(branches) => {
+                for branch in branches {
+                    let (branch_start, branch_end) = self.build(branch, rule);
+                    self.states[start].epsilon.push(branch_start);
+                    self.states[branch_end].epsilon.push(end);
+                }
+            }
+            Node::Star(inner) | Node::Plus(inner) | Node::Optional(inner) => {
+                let (inner_start, inner_end) = self.build(inner, rule);
+                self.states[start].epsilon.push(inner_start);
+                self.states[inner_end].epsilon.push(end);
+                if !matches!(node, Node::Plus(_)) {
+                    self.states[start].epsilon.push(end);
+                }
+                if !matches!(node, Node::Optional(_)) {
+                    self.states[inner_end].epsilon.push(inner_start);
+                }
+            }
+        }
+        (start, end)
+    }
+    fn closure(&self, states: Vec<usize>) -> Vec<usize> {
+        let mut seen = vec![false; self.states.len()];
+        let mut stack = states;
+        let mut closure = vec![];
+        while let Some(state) = stack.pop() {
+            if seen[state] {
+                continue;
+            }
+            seen[state] = true;
+            closure.push(state);
+            stack.extend(self.states[state].epsilon.iter().copied());
+        }
+        closure.sort_unstable();
+        closure
+    }
+}
+
+struct RawDfa {
+    transitions: Vec<[u16; 256]>,
+    accept: Vec<Option<usize>>,
+    conflicts: Vec<Conflict>,
+}
+
+fn determinize(nfa: &Nfa, start: usize, lazy: &[bool]) -> Result<RawDfa, LexGenError> {
+    // A lazy rule that has matched drops its other states so it cannot grow.
+    let prune = |set: Vec<usize>| -> Vec<usize> {
+        let done: Vec<usize> = set
+            .iter()
+            .filter_map(|state| nfa.states[*state].accept)
+            .filter(|rule| lazy[*rule])
+            .collect();
+        set.into_iter()
+            .filter(|state| {
+                let state = &nfa.states[*state];
+                state.accept.is_some() || !done.contains(&state.rule)
+            })
+            .collect()
+    };
+    let mut ids: BTreeMap<Vec<usize>, u16> = BTreeMap::new();
+    let mut sets: Vec<Vec<usize>> = vec![vec![], prune(nfa.closure(vec![start]))];
+    ids.insert(sets[0].clone(), DEAD);
+    ids.insert(sets[1].clone(), START);
+    // Bytes that no edge tells apart move every state the same way, only
+    // the first byte of each class is followed.
+    let mut signatures: Vec<Vec<bool>> = vec![];
+    let mut class_of = [0; 256];
+    for byte in 0..=255u8 {
+        let signature: Vec<bool> = nfa
+            .states
+            .iter()
+            .flat_map(|state| state.edges.iter())
+            .map(|(set, _)| set.contains(byte))
+            .collect();
+        class_of[byte as usize] = match signatures.iter().position(|seen| *seen == signature) {
+            Some(class) => class,
+            None => {
+                signatures.push(signature);
+                signatures.len() - 1
+            }
+        };
+    }
+    let mut transitions = vec![];
+    let mut index = 0;
+    while index < sets.len() {
+        let mut row = [DEAD; 256];
+        let mut class_ids: Vec<Option<u16>> = vec![None; signatures.len()];
+        for byte in 0..=255u8 {
+            let class = class_of[byte as usize];
+            if let Some(id) = class_ids[class] {
+                row[byte as usize] = id;
+                continue;
+            }
+            let targets: Vec<usize> = sets[index]
+                .iter()
+                .flat_map(|state| nfa.states[*state].edges.iter())
+                .filter(|(set, _)| set.contains(byte))
+                .map(|(_, target)| *target)
+                .collect();
+            let target = prune(nfa.closure(targets));
+            let id = match ids.get(&target) {
+                Some(id) => *id,
+                None => {
+                    let id = u16::try_from(sets.len()).map_err(|_| LexGenError::TooManyStates {
+                        limit: usize::from(u16::MAX) + 1,
+                    })?;
+                    ids.insert(target.clone(), id);
+                    sets.push(target);
+                    id
+                }
+            };
+            class_ids[class] = Some(id);
+            row[byte as usize] = id;
+        }
+        transitions.push(row);
+        index += 1;
+    }
+    let mut conflicts = vec![];
+    let accept: Vec<Option<usize>> = sets
+        .iter()
+        .map(|set| {
+            set.iter()
+                .filter_map(|state| nfa.states[*state].accept)
+                .min()
+        })
+        .collect();
+    let mut seen = vec![];
+    for (state, set) in sets.iter().enumerate() {
+        let mut rules: Vec<usize> = set
+            .iter()
+            .filter_map(|state| nfa.states[*state].accept)
+            .collect();
+        rules.sort_unstable();
+        if state == START as usize {
+            conflicts.extend(
+                rules
+                    .iter()
+                    .map(|rule| Conflict::MatchesEmpty { rule: *rule }),
+            );
+            continue;
+        }
+        for loser in rules.iter().skip(1) {
+            if !seen.contains(&(rules[0], *loser)) {
+                seen.push((rules[0], *loser));
+                conflicts.push(Conflict::Overlap {
+                    winner: rules[0],
+                    loser: *loser,
+                    example: example(
+                        &transitions,
+                        u16::try_from(state).expect("states are numbered with u16"),
+                    ),
+                });
+            }
+        }
+    }
+    let rule_count = lazy.len();
+    for rule in 0..rule_count {
+        if !accept
+            .iter()
+            .enumerate()
+            .any(|(state, accepted)| state != START as usize && *accepted == Some(rule))
+        {
+            conflicts.push(Conflict::Unreachable { rule });
+        }
+    }
+    Ok(RawDfa {
+        transitions,
+        accept,
+        conflicts,
+    })
+}
+
+// Shortest input leading from the start state to `target`.
+fn example(transitions: &[[u16; 256]], target: u16) -> String {
+    let mut previous: Vec<Option<(u16, u8)>> = vec![None; transitions.len()];
+    let mut queue = VecDeque::from([START]);
+    let mut visited = vec![false; transitions.len()];
+    visited[START as usize] = true;
+    while let Some(state) = queue.pop_front() {
+        if state == target {
+            break;
+        }
+        for (byte, next) in transitions[state as usize].iter().enumerate() {
+            if *next != DEAD && !visited[*next as usize] {
+                visited[*next as usize] = true;
+                previous[*next as usize] = Some((state, byte as u8));
+                queue.push_back(*next);
+            }
+        }
+    }
+    let mut bytes = vec![];
+    let mut state = target;
+    while let Some((from, byte)) = previous[state as usize] {
+        bytes.push(byte);
+        state = from;
+    }
+    bytes.reverse();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Moore's partition refinement, the dead and start states keep their ids.
+fn minimize(dfa: RawDfa) -> RawDfa {
+    let count = dfa.accept.len();
+    let mut block: Vec<usize> = dfa
+        .accept
+        .iter()
+        .map(|accept| accept.map_or(0, |rule| rule + 1))
+        .collect();
+    loop {
+        let mut signatures: BTreeMap<(usize, Vec<usize>), usize> = BTreeMap::new();
+        let next: Vec<usize> = (0..count)
+            .map(|state| {
+                let signature = (
+                    block[state],
+                    dfa.transitions[state]
+                        .iter()
+                        .map(|target| block[*target as usize])
+                        .collect(),
+                );
+                let id = signatures.len();
+                *signatures.entry(signature).or_insert(id)
+            })
+            .collect();
+        let stable = signatures.len() == block.iter().collect::<BTreeSet<_>>().len();
+        block = next;
+        if stable {
+            break;
+        }
+    }
+    // Renumber so the blocks of the dead and start states come first.
+    let mut order: Vec<usize> = vec![block[DEAD as usize], block[START as usize]];
+    for state_block in block.iter() {
+        if !order.contains(state_block) {
+            order.push(*state_block);
+        }
+    }
+    // There are no more blocks than states, so the ids still fit.
+    let id = |state: usize| {
+        let block = order.iter().position(|b| *b == block[state]).unwrap();
+        u16::try_from(block).expect("blocks are fewer than states")
+    };
+    let mut transitions = vec![[DEAD; 256]; order.len()];
+    let mut accept = vec![None; order.len()];
+    for state in 0..count {
+        let new = id(state) as usize;
+        for (target, old) in transitions[new].iter_mut().zip(dfa.transitions[state]) {
+            *target = id(old as usize);
+        }
+        accept[new] = dfa.accept[state];
+    }
+    // The dead state must not loop into anything else.
+    transitions[DEAD as usize] = [DEAD; 256];
+    RawDfa {
+        transitions,
+        accept,
+        conflicts: dfa.conflicts,
+    }
+}
+
+fn compress(dfa: RawDfa) -> Dfa {
+    let mut columns: Vec<Vec<u16>> = vec![];
+    let mut classes = [0u8; 256];
+    for byte in 0..256 {
+        let column: Vec<u16> = dfa.transitions.iter().map(|row| row[byte]).collect();
+        let class = match columns.iter().position(|existing| *existing == column) {
+            Some(class) => class,
+            None => {
+                columns.push(column);
+                columns.len() - 1
+            }
+        };
+        classes[byte] = class as u8;
+    }
+    let class_count = columns.len();
+    let mut transitions = vec![DEAD; dfa.accept.len() * class_count];
+    for (class, column) in columns.iter().enumerate() {
+        for (state, target) in column.iter().enumerate() {
+            transitions[state * class_count + class] = *target;
+        }
+    }
+    Dfa {
+        classes,
+        class_count,
+        transitions,
+        accept: dfa.accept,
+        conflicts: dfa.conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_match_then_rule_order() {
+        let dfa = generate(&[r"^a", r"^ab", r"^[a-z]+", r"^\w+"]).unwrap();
+        assert_eq!(dfa.find(b"ab"), Some((2, 1)));
+        assert_eq!(dfa.find(b"abc;"), Some((3, 2)));
+        assert_eq!(dfa.find(b"a"), Some((1, 0)));
+        assert_eq!(dfa.find(b";"), None);
+    }
+
+    #[test]
+    fn test_lazy_rule_stops_at_first_match() {
+        let dfa = generate(&[r"^\/\*[\s\S]*?\*\/"]).unwrap();
+        assert_eq!(dfa.find(b"/* a */ b */"), Some((7, 0)));
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let dfa = generate(&[r"^\d+", r"^[0-9]+", r"^\s*"]).unwrap();
+        assert_eq!(
+            dfa.conflicts,
+            vec![
+                Conflict::MatchesEmpty { rule: 2 },
+                Conflict::Overlap {
+                    winner: 0,
+                    loser: 1,
+                    example: "0".to_owned()
+                },
+                Conflict::Unreachable { rule: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_whole_characters() {
+        let dfa = generate(&[r"^.", r"^[^a]b", r"^\W+"]).unwrap();
+        assert_eq!(dfa.find("é".as_bytes()), Some((2, 0)));
+        assert_eq!(dfa.find("☃b".as_bytes()), Some((4, 1)));
+        assert_eq!(dfa.find("🦀🦀;a".as_bytes()), Some((9, 2)));
+        assert_eq!(dfa.find("\n".as_bytes()), Some((1, 2)));
+        let dfa = generate(&[r"^\s+", r"^\S+"]).unwrap();
+        assert_eq!(dfa.find(" \u{a0}\u{3000}é".as_bytes()), Some((6, 0)));
+        assert_eq!(dfa.find("é\u{2027}\u{2028}".as_bytes()), Some((5, 1)));
+    }
+
+    #[test]
+    fn test_alternation_takes_the_longest_branch() {
+        // `regex` would stop after `a`, see the module comment.
+        let dfa = generate(&[r"^a|^ab"]).unwrap();
+        assert_eq!(dfa.find(b"ab"), Some((2, 0)));
+    }
+
+    #[test]
+    fn test_minimized() {
+        let dfa = generate(&[r"^(a|b)*c|^[ab]*c"]).unwrap();
+        // Dead, start and accepting states.
+        assert_eq!(dfa.state_count(), 3);
+    }
+
+    #[test]
+    fn test_unsupported_syntax() {
+        assert!(matches!(
+            generate(&[r"^\d{2}"]),
+            Err(LexGenError::Unsupported { rule: 0, .. })
+        ));
+        assert!(matches!(
+            generate(&[r"^\d", r"^(\d"]),
+            Err(LexGenError::Syntax { rule: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_too_many_states() {
+        // Remembers the last 17 letters, one state for each combination.
+        let pattern = format!("^[ab]*a{}", "[ab]".repeat(16));
+        assert_eq!(
+            generate(&[&pattern]).map(|dfa| dfa.state_count()),
+            Err(LexGenError::TooManyStates { limit: 65536 })
+        );
+    }
+}
@@ -0,0 +1,22 @@
+pub mod ast_diff;
+pub mod codegen;
+pub mod config;
+pub mod cst;
+pub mod dfa_tokenizer;
+pub mod diagnostics;
+pub mod doc;
+pub mod dot;
+pub mod early_errors;
+pub mod estree;
+pub mod formatter;
+pub mod lexgen;
+pub mod minifier;
+pub mod models;
+pub mod parser;
+pub mod parser_factory;
+pub mod repl;
+pub mod sexpr;
+pub mod sourcemap;
+pub mod token_dump;
+pub mod tokenizer;
+pub mod visit;
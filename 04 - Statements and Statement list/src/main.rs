@@ -1,44 +1,595 @@
-#[macro_use]
-extern crate maplit;
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
 
-use std::io;
+use building_parser_from_scratch::{
+    ast_diff::{diff as ast_diff, summary},
+    codegen::QuoteStyle,
+    cst,
+    diagnostics::{render, Diagnostic, Style},
+    dot::to_dot,
+    early_errors::{check, check_strict},
+    estree::{to_value, OutputFormat},
+    formatter::{format, format_program, FormatOptions},
+    minifier::minify,
+    models::DomainError,
+    parser::ParseOptions,
+    parser_factory::ParserFactory,
+    repl::{History, Repl, Reply},
+    sexpr::to_sexpr,
+    token_dump::{collect, diff, dump, DumpFormat, DumpOptions},
+};
 
-use tokenizer::TokenType;
+const USAGE: &str =
+    "usage: building_parser_from_scratch parse [--tokens|--ast|--estree|--sexpr|--dot|--cst|--pretty]
+           [--strict]
+           [--recover] [--asi] [--glob PATTERN] [PATH]...
+       building_parser_from_scratch tokens [--spec FILE] [--format table|jsonl] [--kind KIND]...
+           [--trivia] [--diff OLD NEW] [FILE]
+       building_parser_from_scratch diff BEFORE AFTER
+       building_parser_from_scratch repl [--history FILE]
+       building_parser_from_scratch format [--width N] [--single-quote] [--write] [FILE]...
+       building_parser_from_scratch minify [FILE]";
 
-use crate::parser::Parser;
-mod models;
-mod parser;
-mod parser_factory;
-mod tokenizer;
 fn main() {
-    // let spec = hashmap![
-    //     r"^\s+".to_owned() => TokenType::Null,
-    //     // Comments
-    //     r"^\/\?.*/".to_owned() => TokenType::Null,
-    //     // Comments multi line
-    //     r"^\/\*[\s\S]*?\*\/".to_owned() => TokenType::Null,
-    //     r"^\d+".to_owned() => TokenType::Number,
-    //     r#""([^"]*)"|'([^']*)'"# .to_owned()=> TokenType::String,
-    // ];
-    // let mut parser = Parser::new(
-    //     spec,
-    //     r#"
-    //     //comments
-    //     /**
-    //      * comments
-    //      */
-    //  "  hola    "
-    //   "#
-    //     .to_string(),
-    // );
-    // let result = parser.parse();
-    // match result {
-    //     Ok(parsed) => {
-    //         serde_json::to_writer_pretty(io::stdout(), &parsed)
-    //             .expect("Failed to write JSON to stdout");
-    //     }
-    //     Err(e) => {
-    //         println!("Error: {:?}", e);
-    //     }
-    // }
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match args.split_first() {
+        Some((command, args)) if command == "parse" => parse_command(args),
+        Some((command, args)) if command == "tokens" => tokens_command(args),
+        Some((command, args)) if command == "diff" => diff_command(args),
+        Some((command, args)) if command == "repl" => repl_command(args),
+        Some((command, args)) if command == "format" => format_command(args),
+        Some((command, args)) if command == "minify" => minify_command(args),
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    };
+    process::exit(code);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Tokens,
+    Ast,
+    Estree,
+    Sexpr,
+    Dot,
+    Cst,
+    Pretty,
+}
+
+// Parses each input and prints it in the chosen form, diagnostics go to
+// stderr. Directories are searched for files matching `--glob`, "-" or no
+// path at all reads stdin. Fails when any input has an error.
+fn parse_command(args: &[String]) -> i32 {
+    let mut output = Output::Ast;
+    let mut options = ParseOptions::default();
+    let mut strict = false;
+    let mut glob = "*.js".to_owned();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tokens" => output = Output::Tokens,
+            "--ast" => output = Output::Ast,
+            "--estree" => output = Output::Estree,
+            "--sexpr" => output = Output::Sexpr,
+            "--dot" => output = Output::Dot,
+            "--cst" => output = Output::Cst,
+            "--pretty" => output = Output::Pretty,
+            "--strict" => strict = true,
+            "--recover" => options.recover = true,
+            "--asi" => options.asi = true,
+            "--glob" => match args.next() {
+                Some(pattern) => glob = pattern.clone(),
+                None => {
+                    eprintln!("--glob expects a pattern\n{}", USAGE);
+                    return 2;
+                }
+            },
+            _ if arg.starts_with("--") => {
+                eprintln!("unknown option {}\n{}", arg, USAGE);
+                return 2;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("-"));
+    }
+    let mut inputs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            if let Err(e) = find_files(&path, &glob, &mut inputs) {
+                eprintln!("{}: {}", path.display(), e);
+                return 1;
+            }
+        } else {
+            inputs.push(path);
+        }
+    }
+    let mut code = 0;
+    for input in inputs.iter() {
+        let (name, source) = if input.as_os_str() == "-" {
+            let mut source = String::new();
+            (
+                "<stdin>".to_owned(),
+                io::stdin().read_to_string(&mut source).map(|_| source),
+            )
+        } else {
+            (input.display().to_string(), fs::read_to_string(input))
+        };
+        let Some(source) = loaded(&name, source.map_err(|e| e.to_string())) else {
+            code = 1;
+            continue;
+        };
+        if inputs.len() > 1 {
+            println!("==> {} <==", name);
+        }
+        if !parse_source(&name, &source, output, options, strict) {
+            code = 1;
+        }
+    }
+    code
+}
+
+// Prints one input, returns false when it has an error.
+fn parse_source(
+    name: &str,
+    source: &str,
+    output: Output,
+    options: ParseOptions,
+    strict: bool,
+) -> bool {
+    if output == Output::Tokens {
+        let stream = collect(ParserFactory::tokenizer(source.to_owned()), source, false);
+        print!("{}", dump(&stream, &DumpOptions::default()));
+        if let Some(e) = &stream.error {
+            report(name, source, e);
+        }
+        return stream.error.is_none();
+    }
+    if output == Output::Cst {
+        let tree = cst::parse(source, options);
+        print!("{}", tree.root.debug_tree());
+        let diagnostics: Vec<Diagnostic> =
+            tree.errors.iter().map(DomainError::to_diagnostic).collect();
+        show(name, source, &diagnostics);
+        return diagnostics.is_empty();
+    }
+    let mut parser = ParserFactory::create(source.to_owned()).with_options(options);
+    let parsed = parser.parse();
+    let mut diagnostics: Vec<Diagnostic> = parser
+        .errors()
+        .iter()
+        .map(DomainError::to_diagnostic)
+        .collect();
+    let program = match parsed {
+        Ok(program) => program,
+        Err(e) => {
+            diagnostics.push(e.to_diagnostic());
+            show(name, source, &diagnostics);
+            return false;
+        }
+    };
+    diagnostics.extend(if strict {
+        check_strict(&program, source)
+    } else {
+        check(&program, source)
+    });
+    show(name, source, &diagnostics);
+    match output {
+        Output::Ast | Output::Estree => {
+            let format = if output == Output::Ast {
+                OutputFormat::Legacy
+            } else {
+                OutputFormat::Estree
+            };
+            let value = to_value(&program, format, Some(source));
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&value).expect("JSON values are serializable")
+            );
+        }
+        Output::Sexpr => println!("{}", to_sexpr(&program)),
+        Output::Dot => print!("{}", to_dot(&program)),
+        Output::Pretty => match format_program(&program, source, &FormatOptions::default()) {
+            Ok(formatted) => print!("{}", formatted),
+            Err(e) => {
+                report(name, source, &e);
+                return false;
+            }
+        },
+        Output::Tokens | Output::Cst => unreachable!("printed above"),
+    }
+    diagnostics.is_empty()
+}
+
+// Adds the files under `directory` whose name matches `glob` to `found`, in
+// path order.
+fn find_files(directory: &Path, glob: &str, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_files(&path, glob, found)?;
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| glob_match(glob.as_bytes(), name.as_bytes()))
+        {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+// `*` matches any run of bytes, `?` a single one. On a mismatch the last `*`
+// takes one more byte, so this runs in O(pattern * name).
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it matched up to.
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, n));
+            }
+            Some(&expected) if expected == b'?' || expected == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    star = Some((star_p, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&byte| byte == b'*')
+}
+
+// Prints the tokens of the file, or stdin, as produced by the bundled spec or
+// `--spec`. `--diff` prints the tokens that change between two specs instead,
+// and fails when there are some.
+fn tokens_command(args: &[String]) -> i32 {
+    let mut options = DumpOptions::default();
+    let mut spec = None;
+    let mut specs = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--spec" => match args.next() {
+                Some(path) => spec = Some(path.clone()),
+                None => {
+                    eprintln!("--spec expects a file\n{}", USAGE);
+                    return 2;
+                }
+            },
+            "--format" => match args.next().map(String::as_str) {
+                Some("table") => options.format = DumpFormat::Table,
+                Some("jsonl") => options.format = DumpFormat::JsonLines,
+                _ => {
+                    eprintln!("--format expects table or jsonl\n{}", USAGE);
+                    return 2;
+                }
+            },
+            "--kind" => match args.next() {
+                Some(kind) => options.kinds.push(kind.clone()),
+                None => {
+                    eprintln!("--kind expects a token kind\n{}", USAGE);
+                    return 2;
+                }
+            },
+            "--trivia" => options.trivia = true,
+            "--diff" => match (args.next(), args.next()) {
+                (Some(old), Some(new)) => specs = Some((old.clone(), new.clone())),
+                _ => {
+                    eprintln!("--diff expects two spec files\n{}", USAGE);
+                    return 2;
+                }
+            },
+            _ if arg.starts_with("--") => {
+                eprintln!("unknown option {}\n{}", arg, USAGE);
+                return 2;
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    if let Err(e) = options.validate() {
+        report("", "", &e);
+        return 2;
+    }
+    let Some((name, source)) = read_source(&files) else {
+        return 2;
+    };
+    let Some(source) = loaded(name, source) else {
+        return 1;
+    };
+    let stream = |spec: Option<&str>| {
+        let tokenizer = match spec {
+            Some(path) => ParserFactory::tokenizer_from_config(path, source.clone()),
+            None => Ok(ParserFactory::tokenizer(source.clone())),
+        };
+        tokenizer.map(|tokenizer| collect(tokenizer, &source, options.trivia))
+    };
+    match specs {
+        Some((old, new)) => match (stream(Some(&old)), stream(Some(&new))) {
+            (Ok(old), Ok(new)) => {
+                let changes = diff(&old, &new, &options);
+                print!("{}", changes);
+                i32::from(!changes.is_empty())
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                report("", "", &e);
+                2
+            }
+        },
+        None => match stream(spec.as_deref()) {
+            Ok(stream) => {
+                print!("{}", dump(&stream, &options));
+                match &stream.error {
+                    Some(e) => {
+                        report(name, &source, e);
+                        1
+                    }
+                    None => 0,
+                }
+            }
+            Err(e) => {
+                report("", "", &e);
+                2
+            }
+        },
+    }
+}
+
+// Prints what changed in the syntax tree between two files. Fails when
+// something did, like diff(1).
+fn diff_command(args: &[String]) -> i32 {
+    let [before, after] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let mut programs = Vec::new();
+    for file in [before, after] {
+        let Some(source) = loaded(file, fs::read_to_string(file).map_err(|e| e.to_string())) else {
+            return 2;
+        };
+        match ParserFactory::create(source.clone()).parse() {
+            Ok(program) => programs.push(program),
+            Err(e) => {
+                report(file, &source, &e);
+                return 2;
+            }
+        }
+    }
+    let changes = ast_diff(&programs[0], &programs[1]);
+    print!("{}", summary(&changes));
+    i32::from(!changes.is_empty())
+}
+
+// Reads inputs from stdin until `:quit` or the end of the input. History
+// goes to `.repl_history` in the working directory unless `--history` names
+// another file.
+fn repl_command(args: &[String]) -> i32 {
+    let path = match args {
+        [] => ".repl_history",
+        [option, path] if option == "--history" => path.as_str(),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let history = match History::load(path) {
+        Ok(history) => history,
+        Err(e) => {
+            report("", "", &e);
+            return 1;
+        }
+    };
+    let mut repl = Repl::with_history(style(&io::stdout()), history);
+    let mut line = String::new();
+    loop {
+        print!("{}", repl.prompt());
+        if let Err(e) = io::stdout().flush() {
+            eprintln!("{}", e);
+            return 1;
+        }
+        line.clear();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => {
+                println!();
+                return 0;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+        match repl.feed(line.trim_end_matches(['\n', '\r'])) {
+            Reply::Incomplete => {}
+            Reply::Output(output) => print!("{}", output),
+            Reply::Error(error) => eprint!("{}", error),
+            Reply::Quit => return 0,
+        }
+    }
+}
+
+// Formats each file to stdout, or in place with `--write`. Reads stdin when
+// no file is given.
+fn format_command(args: &[String]) -> i32 {
+    let mut options = FormatOptions::default();
+    let mut write = false;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => match args.next().and_then(|width| width.parse().ok()) {
+                Some(width) => options.width = width,
+                None => {
+                    eprintln!("--width expects a number\n{}", USAGE);
+                    return 2;
+                }
+            },
+            "--single-quote" => options.quote = QuoteStyle::Single,
+            "--write" => write = true,
+            _ if arg.starts_with("--") => {
+                eprintln!("unknown option {}\n{}", arg, USAGE);
+                return 2;
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    if files.is_empty() {
+        let mut source = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut source) {
+            eprintln!("<stdin>: {}", e);
+            return 1;
+        }
+        return match format(&source, &options) {
+            Ok(formatted) => {
+                print!("{}", formatted);
+                0
+            }
+            Err(e) => {
+                report("<stdin>", &source, &e);
+                1
+            }
+        };
+    }
+    let mut code = 0;
+    for file in files.iter() {
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                code = 1;
+                continue;
+            }
+        };
+        let formatted = match format(&source, &options) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                report(file, &source, &e);
+                code = 1;
+                continue;
+            }
+        };
+        let result = if write {
+            fs::write(file, formatted)
+        } else {
+            io::stdout().write_all(formatted.as_bytes())
+        };
+        if let Err(e) = result {
+            eprintln!("{}: {}", file, e);
+            code = 1;
+        }
+    }
+    code
+}
+
+// Prints the minified file, or stdin, and reports the sizes on stderr.
+fn minify_command(args: &[String]) -> i32 {
+    let Some((name, source)) = read_source(args) else {
+        return 2;
+    };
+    let Some(source) = loaded(name, source) else {
+        return 1;
+    };
+    match minify(&source) {
+        Ok(minified) => {
+            println!("{}", minified.code);
+            eprintln!(
+                "{}: {} -> {} bytes",
+                name, minified.original_size, minified.minified_size
+            );
+            0
+        }
+        Err(diagnostics) => {
+            show(name, &source, &diagnostics);
+            1
+        }
+    }
+}
+
+// Name and content of the single file in `args`, stdin when there is none.
+// `None` after printing the usage when more files are given.
+fn read_source(args: &[String]) -> Option<(&str, Result<String, String>)> {
+    let (name, source) = match args {
+        [] => {
+            let mut source = String::new();
+            (
+                "<stdin>",
+                io::stdin().read_to_string(&mut source).map(|_| source),
+            )
+        }
+        [file] => (file.as_str(), fs::read_to_string(file)),
+        _ => {
+            eprintln!("{}", USAGE);
+            return None;
+        }
+    };
+    Some((name, source.map_err(|e| e.to_string())))
+}
+
+// The source of `read_source`, `None` after reporting why it couldn't be
+// read.
+fn loaded(name: &str, source: Result<String, String>) -> Option<String> {
+    source.map_err(|e| eprintln!("{}: {}", name, e)).ok()
+}
+
+// Prints `error` as a code frame of `source` on stderr.
+fn report(name: &str, source: &str, error: &DomainError) {
+    show(name, source, &[error.to_diagnostic()]);
+}
+
+fn show(name: &str, source: &str, diagnostics: &[Diagnostic]) {
+    let style = style(&io::stderr());
+    for diagnostic in diagnostics {
+        eprint!("{}", render(diagnostic, name, source, style));
+    }
+}
+
+// Colors for terminals, unless NO_COLOR is set, plain text for pipes and
+// files.
+fn style(stream: &impl IsTerminal) -> Style {
+    if stream.is_terminal() && env::var_os("NO_COLOR").is_none() {
+        Style::Color
+    } else {
+        Style::Plain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let matches = |pattern: &str, name: &str| glob_match(pattern.as_bytes(), name.as_bytes());
+        assert!(matches("*.js", "a.js"));
+        assert!(matches("*.js", ".js"));
+        assert!(!matches("*.js", "a.json"));
+        assert!(matches("test_?.*", "test_1.js"));
+        assert!(!matches("test_?.*", "test_12"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXbYc"));
+        assert!(!matches("a*b*c", "aXbY"));
+        let long = "a".repeat(10_000);
+        assert!(!matches("*a*a*a*a*b", &long));
+        assert!(matches("*a*a*a*a*", &long));
+    }
 }
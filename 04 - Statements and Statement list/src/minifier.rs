@@ -0,0 +1,172 @@
+// Minification pipeline: AST passes, then compact printing.
+//
+// The grammar only has literal expression statements, so there are no
+// identifiers to mangle and no operators to fold yet. What can go is every
+// statement whose value is never observed, and the printer already drops
+// comments, whitespace and leading zeros. Programs with early errors are
+// refused, dropping the offending statements would make them valid.
+
+use crate::{
+    codegen::{print, CodegenOptions},
+    diagnostics::Diagnostic,
+    early_errors::check,
+    models::{DomainError, Expression, Literal, Program, Statement},
+    parser_factory::ParserFactory,
+    visit::Fold,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Minified {
+    pub code: String,
+    // Sizes in bytes of the input and of `code`.
+    pub original_size: usize,
+    pub minified_size: usize,
+}
+
+pub fn minify(source: &str) -> Result<Minified, Vec<Diagnostic>> {
+    let diagnostic = |e: DomainError| vec![e.to_diagnostic()];
+    let program = ParserFactory::create(source.to_owned())
+        .parse()
+        .map_err(diagnostic)?;
+    let early_errors = check(&program, source);
+    if !early_errors.is_empty() {
+        return Err(early_errors);
+    }
+    let code = print(
+        &minify_program(program),
+        &CodegenOptions {
+            compact: true,
+            ..CodegenOptions::default()
+        },
+    )
+    .map_err(diagnostic)?;
+    Ok(Minified {
+        original_size: source.len(),
+        minified_size: code.len(),
+        code,
+    })
+}
+
+pub fn minify_program(program: Program) -> Program {
+    DropUnused.fold_program(program)
+}
+
+// Literal statements have no side effects, only the directive prologue and
+// the completion value (the last statement) are observable.
+pub struct DropUnused;
+
+impl Fold for DropUnused {
+    fn fold_program(&mut self, mut program: Program) -> Program {
+        let prologue = program
+            .body
+            .iter()
+            .take_while(|statement| is_string(statement))
+            .count();
+        if program.body.len() <= prologue + 1 {
+            return program;
+        }
+        let last = program
+            .body
+            .pop()
+            .expect("body is longer than the prologue");
+        // A string right after the prologue would turn into a directive, the
+        // statement ending the prologue is kept to separate them.
+        let keep = if is_string(&last) {
+            prologue + 1
+        } else {
+            prologue
+        };
+        program.body.truncate(keep);
+        program.body.push(last);
+        program
+    }
+}
+
+fn is_string(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::Expression(statement)
+            if matches!(statement.expression, Expression::Literal(Literal::String(_)))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ParsedValue;
+
+    // Reference semantics of a script: its directives and its completion
+    // value.
+    fn evaluate(source: &str) -> (Vec<String>, Option<ParsedValue>) {
+        let program = ParserFactory::create(source.to_owned()).parse().unwrap();
+        let directives = program
+            .body
+            .iter()
+            .take_while(|statement| is_string(statement))
+            .map(|statement| match statement {
+                Statement::Expression(statement) => match &statement.expression {
+                    Expression::Literal(Literal::String(literal)) => literal.value.clone(),
+                    _ => unreachable!(),
+                },
+            })
+            .collect();
+        let completion = program.body.last().map(|statement| match statement {
+            Statement::Expression(statement) => match &statement.expression {
+                Expression::Literal(Literal::Numeric(literal)) => {
+                    ParsedValue::Number(literal.value)
+                }
+                Expression::Literal(Literal::String(literal)) => {
+                    ParsedValue::String(literal.value.clone())
+                }
+            },
+        });
+        (directives, completion)
+    }
+
+    #[test]
+    fn test_minify() {
+        let source = "// header\n'use strict';\n\n  7; /* x */ 'a';\n42;\n";
+        let minified = minify(source).unwrap();
+        assert_eq!(minified.code, "\"use strict\";42;");
+        assert_eq!(minified.original_size, source.len());
+        assert_eq!(minified.minified_size, minified.code.len());
+    }
+
+    #[test]
+    fn test_early_errors() {
+        let errors = minify("'use strict'; 007; 1;").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some("E0400"));
+        assert_eq!(minify("007; 1;").unwrap().code, "1;");
+    }
+
+    #[test]
+    fn test_keeps_prologue_separate() {
+        assert_eq!(
+            minify("'a'; 1; 2; 'use strict';").unwrap().code,
+            "\"a\";1;\"use strict\";"
+        );
+        assert_eq!(
+            minify("1; 2; 'use strict';").unwrap().code,
+            "1;\"use strict\";"
+        );
+        assert_eq!(minify("'a'; 'b';").unwrap().code, "\"a\";\"b\";");
+    }
+
+    #[test]
+    fn test_preserves_semantics() {
+        for source in [
+            "42;",
+            "'use strict'; 'b'; 1; 2; 3;",
+            "1; 'use strict';",
+            "'a'; 1; 'b';",
+            "1; 2; 3;",
+            "'x'; 'y'; 'z';",
+            "'say \"hi\"'; /* c */ 0; \"it's\";",
+        ] {
+            let minified = minify(source).unwrap();
+            assert_eq!(evaluate(&minified.code), evaluate(source), "{:?}", source);
+            assert!(minified.minified_size <= minified.original_size);
+        }
+    }
+}
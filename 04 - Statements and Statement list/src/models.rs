@@ -1,37 +1,75 @@
-use std::num::ParseIntError;
+use std::{fmt, marker::PhantomData, num::ParseIntError};
 
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::tokenizer::TokenType;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DomainError {
     ParseIntError,
+    ConfigIo(String),
+    ConfigSyntax(String),
+    UnsupportedConfigFormat(String),
+    InvalidRegex {
+        regex: String,
+        message: String,
+    },
+    UnknownTokenKind(String),
+    MissingTokenKind {
+        regex: String,
+    },
+    UnexpectedCharacter {
+        character: char,
+        offset: usize,
+    },
+    // A number token whose value doesn't fit, see `DomainError::at`.
+    NumberTooLarge {
+        span: Span,
+    },
+    InvalidUtf8 {
+        offset: usize,
+    },
+    Io(String),
+    UnexpectedToken {
+        expected: Vec<TokenType>,
+        found: TokenType,
+        span: Span,
+    },
+    UnexpectedEndOfInput {
+        expected: Vec<TokenType>,
+        offset: usize,
+    },
+    // The lexer has no escapes, a string holding both quotes has no source form.
+    UnquotableString(String),
+    InvalidSourceMap(String),
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum ParsedType {
-    NumericLiteral,
-    ExpressionStatement,
-    StringLiteral,
+
+// Byte range in the input, `end` is exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+    // Nodes built by transforms rather than the parser have an empty span.
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
 }
+// Value of a token produced by the JavaScript spec.
 #[derive(Debug, Clone, Serialize, PartialEq, Deserialize)]
 pub enum ParsedValue {
     String(String),
     Number(u128),
 }
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-pub enum ParsedValueExpression {
-    ExpressionStatement(Box<Parsed>),
-}
 
-impl Serialize for ParsedValueExpression {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        match self {
-            ParsedValueExpression::ExpressionStatement(parsed) => parsed.serialize(serializer),
-        }
-    }
-}
 impl ParsedValue {
     pub fn get_number(&self) -> u128 {
         match self {
@@ -46,73 +84,371 @@ impl ParsedValue {
         }
     }
 }
+
+impl From<ParseIntError> for DomainError {
+    fn from(_: ParseIntError) -> Self {
+        DomainError::ParseIntError
+    }
+}
+
+impl DomainError {
+    // Places an error of a token converter at the token it converted.
+    pub fn at(self, span: Span) -> DomainError {
+        match self {
+            DomainError::ParseIntError => DomainError::NumberTooLarge { span },
+            e => e,
+        }
+    }
+}
+
+// The tree built by `Parser`. Spans are not serialized, literal values keep
+// the `{"Number": 42}` / `{"String": "..."}` shape of the original output.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub struct Program {
+    pub body: Vec<Statement>,
+    #[serde(skip)]
+    pub span: Span,
+}
+
+impl Program {
+    pub fn new(body: Vec<Statement>, span: Span) -> Program {
+        Program { body, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Statement {
+    #[serde(rename = "ExpressionStatement")]
+    Expression(ExpressionStatement),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Expression(statement) => statement.span,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Parsed {
-    value: ParsedValue,
+pub struct ExpressionStatement {
+    pub expression: Expression,
+    #[serde(skip)]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Expression {
+    Literal(Literal),
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Literal(literal) => literal.span(),
+        }
+    }
+}
 
-    #[serde(rename = "type")]
-    parsed_type: ParsedType,
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Literal {
+    #[serde(rename = "NumericLiteral")]
+    Numeric(NumericLiteral),
+    #[serde(rename = "StringLiteral")]
+    String(StringLiteral),
 }
+
+impl Literal {
+    pub fn span(&self) -> Span {
+        match self {
+            Literal::Numeric(literal) => literal.span,
+            Literal::String(literal) => literal.span,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct ParsedExpression {
-    expression: ParsedValueExpression,
+pub struct NumericLiteral {
+    #[serde(with = "tagged_number")]
+    pub value: u128,
+    #[serde(skip)]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StringLiteral {
+    #[serde(with = "tagged_string")]
+    pub value: String,
+    #[serde(skip)]
+    pub span: Span,
+}
+
+// Structural equality of trees, spans are not compared. Trees read back from
+// JSON or parsed from printed code only differ from the original in spans.
+pub trait EqIgnoreSpan {
+    fn eq_ignore_span(&self, other: &Self) -> bool;
+}
+
+impl<T: EqIgnoreSpan> EqIgnoreSpan for Vec<T> {
+    fn eq_ignore_span(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|(left, right)| left.eq_ignore_span(right))
+    }
+}
 
-    #[serde(rename = "type")]
-    parsed_type: ParsedType,
+impl EqIgnoreSpan for Program {
+    fn eq_ignore_span(&self, other: &Self) -> bool {
+        self.body.eq_ignore_span(&other.body)
+    }
 }
 
-impl ParsedExpression {
-    pub fn new(expression: ParsedValueExpression) -> ParsedExpression {
-        ParsedExpression {
-            parsed_type: ParsedType::ExpressionStatement,
-            expression,
+impl EqIgnoreSpan for Statement {
+    fn eq_ignore_span(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Statement::Expression(left), Statement::Expression(right)) => {
+                left.eq_ignore_span(right)
+            }
         }
     }
 }
-impl Parsed {
-    pub fn new(parsed_type: ParsedType, value: ParsedValue) -> Parsed {
-        Parsed { parsed_type, value }
+
+impl EqIgnoreSpan for ExpressionStatement {
+    fn eq_ignore_span(&self, other: &Self) -> bool {
+        self.expression.eq_ignore_span(&other.expression)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-pub enum ParsedValues {
-    ParsedValue(Parsed),
-    #[serde(rename = "expression")]
-    ParsedExpression(ParsedExpression),
+impl EqIgnoreSpan for Expression {
+    fn eq_ignore_span(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expression::Literal(left), Expression::Literal(right)) => left.eq_ignore_span(right),
+        }
+    }
 }
-// Implementing custom Serialize for ParsedValues
-impl Serialize for ParsedValues {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        match self {
-            ParsedValues::ParsedValue(parsed) => parsed.serialize(serializer),
-            ParsedValues::ParsedExpression(parsed) => parsed.serialize(serializer),
+
+impl EqIgnoreSpan for Literal {
+    fn eq_ignore_span(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Numeric(left), Literal::Numeric(right)) => left.value == right.value,
+            (Literal::String(left), Literal::String(right)) => left.value == right.value,
+            _ => false,
         }
     }
 }
 
-impl From<ParseIntError> for DomainError {
-    fn from(_: ParseIntError) -> Self {
-        DomainError::ParseIntError
+// The tagged nodes are read by hand: serde buffers internally tagged and
+// untagged enums, and its buffer cannot hold the `u128` of a NumericLiteral.
+impl<'de> Deserialize<'de> for Program {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Program, D::Error> {
+        let (node_type, body) = deserializer.deserialize_map(NodeVisitor::new("body"))?;
+        match node_type.as_str() {
+            "Program" => Ok(Program::new(body, Span::default())),
+            _ => Err(de::Error::unknown_variant(&node_type, &["Program"])),
+        }
     }
 }
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
 
-pub struct Program {
-    body: Vec<ParsedValues>,
+impl<'de> Deserialize<'de> for Statement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Statement, D::Error> {
+        let (node_type, expression) =
+            deserializer.deserialize_map(NodeVisitor::new("expression"))?;
+        match node_type.as_str() {
+            "ExpressionStatement" => Ok(Statement::Expression(ExpressionStatement {
+                expression,
+                span: Span::default(),
+            })),
+            _ => Err(de::Error::unknown_variant(
+                &node_type,
+                &["ExpressionStatement"],
+            )),
+        }
+    }
+}
 
-    #[serde(rename = "type")]
-    program_type: String,
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Expression, D::Error> {
+        Literal::deserialize(deserializer).map(Expression::Literal)
+    }
 }
 
-impl Program {
-    pub fn new(body: Vec<ParsedValues>) -> Program {
-        Program {
-            program_type: "Program".to_owned(),
-            body,
+impl<'de> Deserialize<'de> for Literal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Literal, D::Error> {
+        let (node_type, value) = deserializer.deserialize_map(NodeVisitor::new("value"))?;
+        let span = Span::default();
+        match (node_type.as_str(), value) {
+            ("NumericLiteral", ParsedValue::Number(value)) => {
+                Ok(Literal::Numeric(NumericLiteral { value, span }))
+            }
+            ("StringLiteral", ParsedValue::String(value)) => {
+                Ok(Literal::String(StringLiteral { value, span }))
+            }
+            ("NumericLiteral" | "StringLiteral", value) => Err(de::Error::invalid_value(
+                de::Unexpected::Other(&format!("{:?}", value)),
+                &node_type.as_str(),
+            )),
+            _ => Err(de::Error::unknown_variant(
+                &node_type,
+                &["NumericLiteral", "StringLiteral"],
+            )),
         }
     }
 }
+
+// Reads the `type` of a node and its single child `field`, in any order.
+struct NodeVisitor<T> {
+    field: &'static str,
+    child: PhantomData<T>,
+}
+
+impl<T> NodeVisitor<T> {
+    fn new(field: &'static str) -> NodeVisitor<T> {
+        NodeVisitor {
+            field,
+            child: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for NodeVisitor<T> {
+    type Value = (String, T);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a node with `type` and `{}`", self.field)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(String, T), A::Error> {
+        let mut node_type = None;
+        let mut child = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "type" {
+                node_type = Some(map.next_value()?);
+            } else if key == self.field {
+                child = Some(map.next_value()?);
+            } else {
+                map.next_value::<de::IgnoredAny>()?;
+            }
+        }
+        Ok((
+            node_type.ok_or_else(|| de::Error::missing_field("type"))?,
+            child.ok_or_else(|| de::Error::missing_field(self.field))?,
+        ))
+    }
+}
+
+// `{"Number": 42}`
+mod tagged_number {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    enum Tagged {
+        Number(u128),
+    }
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        Tagged::Number(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        let Tagged::Number(value) = Tagged::deserialize(deserializer)?;
+        Ok(value)
+    }
+}
+
+// `{"String": "..."}`
+mod tagged_string {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    enum Tagged {
+        String(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        Tagged::String(value.to_owned()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let Tagged::String(value) = Tagged::deserialize(deserializer)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::parser_factory::ParserFactory;
+
+    fn literal() -> impl Strategy<Value = String> {
+        prop_oneof![
+            any::<u128>().prop_map(|value| value.to_string()),
+            "[a-z0-9 '/*é\\\n]{0,12}".prop_map(|value| format!("\"{}\"", value)),
+            "[a-z0-9 \"/*é\\\n]{0,12}".prop_map(|value| format!("'{}'", value)),
+        ]
+    }
+
+    fn separator() -> impl Strategy<Value = &'static str> {
+        prop::sample::select(vec!["", " ", "\n\t", " // line\n", "/* block */"])
+    }
+
+    fn source() -> impl Strategy<Value = String> {
+        prop::collection::vec((separator(), literal()), 1..8).prop_map(|statements| {
+            statements
+                .into_iter()
+                .map(|(separator, literal)| format!("{}{};", separator, literal))
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_json_round_trip(source in source()) {
+            let program = ParserFactory::create(source).parse().unwrap();
+            let json = serde_json::to_string(&program).unwrap();
+            let read_back: Program = serde_json::from_str(&json).unwrap();
+            prop_assert!(read_back.eq_ignore_span(&program));
+            prop_assert_eq!(serde_json::to_string(&read_back).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_legacy_json() {
+        let program: Program = serde_json::from_str(
+            r#"{
+                "type": "Program",
+                "body": [
+                    {
+                        "type": "ExpressionStatement",
+                        "expression": {"type": "NumericLiteral", "value": {"Number": 42}}
+                    },
+                    {
+                        "type": "ExpressionStatement",
+                        "expression": {"type": "StringLiteral", "value": {"String": "a"}}
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let parsed = ParserFactory::create("42; 'a';".to_owned())
+            .parse()
+            .unwrap();
+        assert!(program.eq_ignore_span(&parsed));
+        assert_ne!(program, parsed);
+    }
+
+    #[test]
+    fn test_deserialize_rejects_mismatched_values() {
+        assert!(serde_json::from_str::<Literal>(
+            r#"{"type": "NumericLiteral", "value": {"String": "42"}}"#
+        )
+        .is_err());
+    }
+}
@@ -1,104 +1,260 @@
-use std::collections::{BTreeMap, HashMap};
 use indexmap::IndexMap;
 
 use crate::{
+    cst::{GreenBuilder, GreenNode, SyntaxKind},
     models::{
-        DomainError, Parsed, ParsedExpression, ParsedType, ParsedValue, ParsedValueExpression,
-        ParsedValues, Program,
+        DomainError, Expression, ExpressionStatement, Literal, NumericLiteral, Program, Span,
+        Statement, StringLiteral,
     },
-    tokenizer::{TokenType, TokenValue, Tokenizer},
+    tokenizer::{SpecWarning, TokenSource, TokenType, TokenValue, Tokenizer},
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    // Skip to the next `;` after a syntax error and go on, the errors are
+    // kept in `Parser::errors`.
+    pub recover: bool,
+    // Automatic semicolon insertion: a statement may end without `;` before
+    // a line break or the end of the input.
+    pub asi: bool,
+}
+
 pub struct Parser {
-    tokenizer: Tokenizer,
+    tokenizer: Box<dyn TokenSource>,
     look_ahead: Option<TokenValue>,
+    // Whether a line break comes before `look_ahead`.
+    newline_before: bool,
+    options: ParseOptions,
+    errors: Vec<DomainError>,
+    // Tokenizer error met past a consumed token, raised when the parser next
+    // needs a token so that the node holding that token is still completed.
+    tokenizer_error: Option<DomainError>,
+    // Records the syntax tree, see `with_syntax_tree`.
+    builder: Option<GreenBuilder>,
 }
 impl Parser {
     pub fn new(spec: IndexMap<String, TokenType>, to_parse: String) -> Parser {
+        let rules = TokenType::rules(spec).unwrap_or_else(|e| panic!("Invalid spec: {:?}", e));
+        Parser::from_tokenizer(Tokenizer::new(rules, to_parse))
+    }
+    pub fn from_tokenizer(tokenizer: impl TokenSource + 'static) -> Parser {
         Parser {
-            tokenizer: Tokenizer::new(spec, to_parse.clone()),
+            tokenizer: Box::new(tokenizer),
             look_ahead: None,
+            newline_before: false,
+            options: ParseOptions::default(),
+            errors: vec![],
+            tokenizer_error: None,
+            builder: None,
         }
     }
+    pub fn with_options(mut self, options: ParseOptions) -> Parser {
+        self.options = options;
+        self
+    }
+    // Also records the lossless syntax tree of `source`, the input the
+    // tokenizer reads, for `finish_syntax_tree`.
+    pub(crate) fn with_syntax_tree(mut self, source: String) -> Parser {
+        self.tokenizer.keep_trivia();
+        self.builder = Some(GreenBuilder::new(source));
+        self
+    }
+    // The syntax tree recorded by `parse`. After a tokenizer error, the rest
+    // of the input is kept in an error token.
+    pub(crate) fn finish_syntax_tree(&mut self) -> Option<GreenNode> {
+        let mut builder = self.builder.take()?;
+        builder.trivia(self.tokenizer.take_trivia());
+        Some(builder.finish())
+    }
+    pub fn warnings(&self) -> &[SpecWarning] {
+        self.tokenizer.warnings()
+    }
+    // Syntax errors recovered from, see `ParseOptions::recover`.
+    pub fn errors(&self) -> &[DomainError] {
+        &self.errors
+    }
+    // With `recover`, the program holds the statements parsed without error.
+    // Tokenizer errors still stop the parse.
     pub fn parse(&mut self) -> Result<Program, DomainError> {
-        self.look_ahead = self.tokenizer.get_next_token();
+        self.advance()?;
         self.program()
     }
 
     pub fn program(&mut self) -> Result<Program, DomainError> {
-        self.statement_list().map(|x| Program::new(x))
+        self.start_node(SyntaxKind::Program);
+        let body = self.statement_list()?;
+        self.finish_node();
+        Ok(Program::new(body, Span::new(0, self.tokenizer.position())))
     }
     // StatementList
     // : Statement
     // | StatementList Statement -> Statement Statement Statement Statement
     // ;
 
-    pub fn statement_list(&mut self) -> Result<Vec<ParsedValues>, DomainError> {
-        let mut statement_list = vec![self.statement()?];
-        while self.look_ahead.is_some() {
-            statement_list.push(self.statement()?);
+    pub fn statement_list(&mut self) -> Result<Vec<Statement>, DomainError> {
+        let mut statement_list = vec![];
+        loop {
+            let depth = self.builder.as_ref().map_or(0, GreenBuilder::depth);
+            match self.statement() {
+                Ok(statement) => statement_list.push(statement),
+                Err(
+                    e @ (DomainError::UnexpectedToken { .. }
+                    | DomainError::UnexpectedEndOfInput { .. }),
+                ) if self.options.recover => {
+                    self.errors.push(e);
+                    if let Some(builder) = self.builder.as_mut() {
+                        builder.start_error(depth);
+                    }
+                    self.synchronize()?;
+                    self.finish_node();
+                }
+                Err(e) => return Err(e),
+            }
+            if self.look_ahead.is_none() {
+                return match self.tokenizer_error.take() {
+                    Some(e) => Err(e),
+                    None => Ok(statement_list),
+                };
+            }
         }
-        Ok(statement_list)
     }
 
     // Statement
     // : ExpressionStatement
     // ;
-    pub fn statement(&mut self) -> Result<ParsedValues, DomainError> {
-        self.expression_statement()
+    pub fn statement(&mut self) -> Result<Statement, DomainError> {
+        self.expression_statement().map(Statement::Expression)
     }
 
     // ExpressionStatement
     // : Expression ;
     // ;
-    pub fn expression_statement(&mut self) -> Result<ParsedValues, DomainError> {
+    pub fn expression_statement(&mut self) -> Result<ExpressionStatement, DomainError> {
+        self.start_node(SyntaxKind::ExpressionStatement);
         let expression = self.expression()?;
-        let token = self.eat(TokenType::SemiColon);
-        let aa: ParsedValueExpression =
-            ParsedValueExpression::ExpressionStatement(Box::new(expression));
-        let expression_parsed = ParsedExpression::new(aa);
-        let response = ParsedValues::ParsedExpression(expression_parsed);
-        Ok(response)
+        let at_semicolon = matches!(
+            &self.look_ahead,
+            Some(token) if token.token_type == TokenType::SemiColon
+        );
+        let end = if !at_semicolon
+            && self.options.asi
+            && self.tokenizer_error.is_none()
+            && (self.look_ahead.is_none() || self.newline_before)
+        {
+            expression.span().end
+        } else {
+            self.eat(TokenType::SemiColon)?.span.end
+        };
+        self.finish_node();
+        Ok(ExpressionStatement {
+            span: Span::new(expression.span().start, end),
+            expression,
+        })
     }
-    pub fn expression(&mut self) -> Result<Parsed, DomainError> {
-        self.literal()
+    pub fn expression(&mut self) -> Result<Expression, DomainError> {
+        self.literal().map(Expression::Literal)
     }
 
-    pub fn literal(&mut self) -> Result<Parsed, DomainError> {
-        match self.look_ahead.as_ref().unwrap().token_type {
-            TokenType::Number => self.numeric_literal(),
-            TokenType::String => self.string_literal(),
-            TokenType::Null => todo!(),
-            TokenType::SemiColon => todo!(),
+    // Literal
+    // : NumericLiteral
+    // | StringLiteral
+    // ;
+    pub fn literal(&mut self) -> Result<Literal, DomainError> {
+        match self.look_ahead.as_ref().map(|token| &token.token_type) {
+            Some(TokenType::Number) => self.numeric_literal().map(Literal::Numeric),
+            Some(TokenType::String) => self.string_literal().map(Literal::String),
+            _ => Err(self.unexpected(vec![TokenType::Number, TokenType::String])),
         }
     }
     // NumericLiteral
     // : Number
     // ;
-    fn numeric_literal(&mut self) -> Result<Parsed, DomainError> {
-        let token = self.eat(TokenType::Number);
-        Ok(Parsed::new(
-            ParsedType::NumericLiteral,
-            ParsedValue::Number(token.value.get_number()),
-        ))
-    }
-    fn string_literal(&mut self) -> Result<Parsed, DomainError> {
-        let token = self.eat(TokenType::String);
+    fn numeric_literal(&mut self) -> Result<NumericLiteral, DomainError> {
+        self.start_node(SyntaxKind::NumericLiteral);
+        let token = self.eat(TokenType::Number)?;
+        self.finish_node();
+        Ok(NumericLiteral {
+            value: token.value.get_number(),
+            span: token.span,
+        })
+    }
+    // StringLiteral
+    // : String
+    // ;
+    fn string_literal(&mut self) -> Result<StringLiteral, DomainError> {
+        self.start_node(SyntaxKind::StringLiteral);
+        let token = self.eat(TokenType::String)?;
+        self.finish_node();
         let value = token.value.get_string();
-        return Ok(Parsed::new(
-            ParsedType::StringLiteral,
-            ParsedValue::String(value[1..value.len() - 1].to_string()),
-        ));
-    }
-    fn eat(&mut self, token_type: TokenType) -> TokenValue {
-        if self.look_ahead.is_none() {
-            panic!("Unexpected end of input");
+        Ok(StringLiteral {
+            value: value[1..value.len() - 1].to_string(),
+            span: token.span,
+        })
+    }
+    fn eat(&mut self, token_type: TokenType) -> Result<TokenValue, DomainError> {
+        match self.look_ahead.take() {
+            Some(token) if token.token_type == token_type => {
+                self.bump(&token);
+                if let Err(e) = self.advance() {
+                    self.tokenizer_error = Some(e);
+                }
+                Ok(token)
+            }
+            look_ahead => {
+                self.look_ahead = look_ahead;
+                Err(self.unexpected(vec![token_type]))
+            }
+        }
+    }
+    fn advance(&mut self) -> Result<(), DomainError> {
+        self.look_ahead = self.tokenizer.get_next_token()?;
+        self.newline_before = self.tokenizer.newline_before();
+        if let Some(builder) = self.builder.as_mut() {
+            builder.trivia(self.tokenizer.take_trivia());
+        }
+        Ok(())
+    }
+    // Adds a consumed token to the syntax tree.
+    fn bump(&mut self, token: &TokenValue) {
+        if let Some(builder) = self.builder.as_mut() {
+            builder.token(&token.token_type, token.span);
+        }
+    }
+    fn start_node(&mut self, kind: SyntaxKind) {
+        if let Some(builder) = self.builder.as_mut() {
+            builder.start_node(kind);
+        }
+    }
+    fn finish_node(&mut self) {
+        if let Some(builder) = self.builder.as_mut() {
+            builder.finish_node();
+        }
+    }
+    // Drops the tokens up to the next `;`, included.
+    fn synchronize(&mut self) -> Result<(), DomainError> {
+        while let Some(token) = self.look_ahead.take() {
+            self.bump(&token);
+            self.advance()?;
+            if token.token_type == TokenType::SemiColon {
+                break;
+            }
+        }
+        Ok(())
+    }
+    fn unexpected(&mut self, expected: Vec<TokenType>) -> DomainError {
+        if let Some(e) = self.tokenizer_error.take() {
+            return e;
         }
-        let token = self.look_ahead.clone().unwrap();
-        if token.token_type != token_type {
-            panic!("Unexpected token {:?}", token);
+        match &self.look_ahead {
+            Some(token) => DomainError::UnexpectedToken {
+                expected,
+                found: token.token_type.clone(),
+                span: token.span,
+            },
+            None => DomainError::UnexpectedEndOfInput {
+                expected,
+                offset: self.tokenizer.position(),
+            },
         }
-        self.look_ahead = self.tokenizer.get_next_token();
-        token
     }
 }
@@ -1,40 +1,71 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{io::Read, path::Path};
+
 use indexmap::IndexMap;
-use crate::{parser::Parser, tokenizer::TokenType};
+
+use crate::{
+    config::{LexerConfig, DEFAULT_MODE},
+    dfa_tokenizer::DfaTokenizer,
+    models::{DomainError, ParsedValue},
+    parser::Parser,
+    tokenizer::{TokenType, Tokenizer},
+};
 
 pub struct ParserFactory {}
 
 impl ParserFactory {
     pub fn create(to_parse: String) -> Parser {
-        let mut spec = IndexMap::new();
-        spec.insert(r"^\s+".to_owned(), TokenType::Null);
-        spec.insert(r"^\/\*[\s\S]*?\*\/".to_owned(), TokenType::Null);
-        spec.insert(r"^;+".to_owned(), TokenType::SemiColon);
-        spec.insert(r"^\/\?.*/".to_owned(), TokenType::Null);
-        spec.insert(r"^\d+".to_owned(), TokenType::Number);
-        spec.insert(r#""([^"]*)"|'([^']*)'"#.to_owned(), TokenType::String);
-        // let spec = hashmap![
-        //     r"^\s+".to_owned() => TokenType::Null,
-        //     r"^\/\*[\s\S]*?\*\/".to_owned() => TokenType::Null,
-        //     r"^;+".to_owned() => TokenType::SemiColon,
-        //     // Comments
-        //     r"^\/\?.*/".to_owned() => TokenType::Null,
-        //     // Comments multi line
-        //
-        //     r"^\d+".to_owned() => TokenType::Number,
-        //     r#""([^"]*)"|'([^']*)'"# .to_owned()=> TokenType::String,
-        // ];
-        Parser::new(spec, to_parse)
+        Parser::new(ParserFactory::bundled_spec(), to_parse)
+    }
+    // Same as `create`, with the scanner generated from the bundled spec at
+    // build time.
+    pub fn create_dfa(to_parse: String) -> Parser {
+        Parser::from_tokenizer(DfaTokenizer::new(to_parse))
+    }
+    // Parses from a reader, tokens are produced as the parser asks for them.
+    pub fn from_reader(reader: impl Read + 'static) -> Parser {
+        let rules =
+            TokenType::rules(ParserFactory::bundled_spec()).expect("Bundled lexer.toml is invalid");
+        Parser::from_tokenizer(Tokenizer::from_reader(rules, reader))
+    }
+    // Tokenizer over the bundled spec, the one `create` parses with.
+    pub fn tokenizer(to_parse: String) -> Tokenizer<TokenType, ParsedValue> {
+        let rules =
+            TokenType::rules(ParserFactory::bundled_spec()).expect("Bundled lexer.toml is invalid");
+        Tokenizer::new(rules, to_parse)
+    }
+    pub fn from_config(path: impl AsRef<Path>, to_parse: String) -> Result<Parser, DomainError> {
+        ParserFactory::tokenizer_from_config(path, to_parse).map(Parser::from_tokenizer)
+    }
+    pub fn tokenizer_from_config(
+        path: impl AsRef<Path>,
+        to_parse: String,
+    ) -> Result<Tokenizer<TokenType, ParsedValue>, DomainError> {
+        let spec = LexerConfig::load(path)?.spec(DEFAULT_MODE)?;
+        let rules = TokenType::rules(spec)?;
+        Ok(Tokenizer::new(rules, to_parse))
+    }
+    fn bundled_spec() -> IndexMap<String, TokenType> {
+        LexerConfig::from_toml(include_str!("../lexer.toml"))
+            .and_then(|config| config.spec(DEFAULT_MODE))
+            .expect("Bundled lexer.toml is invalid")
     }
 }
 
 // Write the test function
 #[cfg(test)] // This attribute indicates that the following code is only compiled when running tests
 mod tests {
-    use serde_json::{json, to_string_pretty, Value};
+    use serde_json::{json, Value};
 
     // Import the `add` function from the parent module
     use super::*;
+    use crate::{
+        models::{
+            Expression, ExpressionStatement, Literal, NumericLiteral, Program, Span, Statement,
+            StringLiteral,
+        },
+        parser::ParseOptions,
+        sexpr::to_sexpr,
+    };
 
     // Define a test function
     #[test]
@@ -142,4 +173,219 @@ mod tests {
             .to_string();
         assert_eq!(&string_json, assert_value);
     }
+
+    #[test]
+    fn test_comments() {
+        let result = ParserFactory::create(
+            r#"
+            // single line
+            /*
+             * multi line
+             */
+            42;"#
+                .to_string(),
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            json!({
+                "type": "Program",
+                "body": [{
+                    "type": "ExpressionStatement",
+                    "expression": {
+                        "type": "NumericLiteral",
+                        "value": {
+                            "Number": 42
+                        }
+                    }
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_config() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("lexer.toml");
+        let from_config = ParserFactory::from_config(path, "42; 'a';".to_owned())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let created = ParserFactory::create("42; 'a';".to_owned())
+            .parse()
+            .unwrap();
+        assert_eq!(from_config, created);
+    }
+
+    #[test]
+    fn test_from_missing_config() {
+        assert!(matches!(
+            ParserFactory::from_config("missing.toml", "42;".to_owned()),
+            Err(DomainError::ConfigIo(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let to_parse = "' test : ';\n42;\n".repeat(1000);
+        let created = ParserFactory::create(to_parse.clone()).parse().unwrap();
+        let streamed = ParserFactory::from_reader(std::io::Cursor::new(to_parse))
+            .parse()
+            .unwrap();
+        assert_eq!(streamed, created);
+    }
+
+    #[test]
+    fn test_unexpected_character() {
+        assert_eq!(
+            ParserFactory::create("42; @".to_owned()).parse(),
+            Err(DomainError::UnexpectedCharacter {
+                character: '@',
+                offset: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_typed_nodes_and_spans() {
+        let result = ParserFactory::create(" 42; 'a';".to_owned())
+            .parse()
+            .unwrap();
+        assert_eq!(
+            result,
+            Program::new(
+                vec![
+                    Statement::Expression(ExpressionStatement {
+                        expression: Expression::Literal(Literal::Numeric(NumericLiteral {
+                            value: 42,
+                            span: Span::new(1, 3),
+                        })),
+                        span: Span::new(1, 4),
+                    }),
+                    Statement::Expression(ExpressionStatement {
+                        expression: Expression::Literal(Literal::String(StringLiteral {
+                            value: "a".to_owned(),
+                            span: Span::new(5, 8),
+                        })),
+                        span: Span::new(5, 9),
+                    }),
+                ],
+                Span::new(0, 9),
+            )
+        );
+    }
+
+    #[test]
+    fn test_unexpected_token() {
+        assert_eq!(
+            ParserFactory::create("42 42;".to_owned()).parse(),
+            Err(DomainError::UnexpectedToken {
+                expected: vec![TokenType::SemiColon],
+                found: TokenType::Number,
+                span: Span::new(3, 5),
+            })
+        );
+        assert_eq!(
+            ParserFactory::create(";".to_owned()).parse(),
+            Err(DomainError::UnexpectedToken {
+                expected: vec![TokenType::Number, TokenType::String],
+                found: TokenType::SemiColon,
+                span: Span::new(0, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn test_unexpected_end_of_input() {
+        assert_eq!(
+            ParserFactory::create("42".to_owned()).parse(),
+            Err(DomainError::UnexpectedEndOfInput {
+                expected: vec![TokenType::SemiColon],
+                offset: 2,
+            })
+        );
+    }
+
+    fn statement_spans(program: &Program) -> Vec<Span> {
+        program
+            .body
+            .iter()
+            .map(|statement| statement.span())
+            .collect()
+    }
+
+    #[test]
+    fn test_recover() {
+        let options = ParseOptions {
+            recover: true,
+            ..ParseOptions::default()
+        };
+        let mut parser = ParserFactory::create("1; 2 3; 'a';;\n4".to_owned()).with_options(options);
+        let program = parser.parse().unwrap();
+        assert_eq!(
+            to_sexpr(&program),
+            r#"(program (expr-stmt 1) (expr-stmt "a"))"#
+        );
+        assert_eq!(
+            statement_spans(&program),
+            [Span::new(0, 2), Span::new(8, 13)]
+        );
+        assert_eq!(
+            parser.errors(),
+            [
+                DomainError::UnexpectedToken {
+                    expected: vec![TokenType::SemiColon],
+                    found: TokenType::Number,
+                    span: Span::new(5, 6),
+                },
+                DomainError::UnexpectedEndOfInput {
+                    expected: vec![TokenType::SemiColon],
+                    offset: 15,
+                },
+            ]
+        );
+        // Tokenizer errors can't be recovered from.
+        assert!(matches!(
+            ParserFactory::create("1; @; 2;".to_owned())
+                .with_options(options)
+                .parse(),
+            Err(DomainError::UnexpectedCharacter { .. })
+        ));
+    }
+
+    #[test]
+    fn test_asi() {
+        let options = ParseOptions {
+            asi: true,
+            ..ParseOptions::default()
+        };
+        let program = ParserFactory::create("1\n'a' // c\n2; 3".to_owned())
+            .with_options(options)
+            .parse()
+            .unwrap();
+        assert_eq!(
+            to_sexpr(&program),
+            r#"(program (expr-stmt 1) (expr-stmt "a") (expr-stmt 2) (expr-stmt 3))"#
+        );
+        assert_eq!(
+            statement_spans(&program),
+            [
+                Span::new(0, 1),
+                Span::new(2, 5),
+                Span::new(11, 13),
+                Span::new(14, 15)
+            ]
+        );
+        // No line break between the literals.
+        assert_eq!(
+            ParserFactory::create("1 2;".to_owned())
+                .with_options(options)
+                .parse(),
+            Err(DomainError::UnexpectedToken {
+                expected: vec![TokenType::SemiColon],
+                found: TokenType::Number,
+                span: Span::new(2, 3),
+            })
+        );
+    }
 }
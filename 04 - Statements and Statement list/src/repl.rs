@@ -0,0 +1,330 @@
+// Line-oriented read-eval-print loop. The caller reads the lines and prints
+// the replies, so the loop itself runs without a terminal.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    diagnostics::{render, Style},
+    estree::{to_value, OutputFormat},
+    models::{DomainError, Program},
+    parser::ParseOptions,
+    parser_factory::ParserFactory,
+    sexpr::to_sexpr,
+    token_dump::{collect, dump, DumpOptions},
+};
+
+const NAME: &str = "<repl>";
+
+const HELP: &str = "\
+:tokens [CODE]  tokens of CODE, or of the last input
+:ast [CODE]     S-expression of CODE, or of the last input
+:json [CODE]    ESTree JSON of CODE, or of the last input
+:load FILE      S-expression of FILE
+:history        inputs entered so far
+:break          drop an incomplete input
+:help           this message
+:quit           leave
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    // The input goes on on the next line.
+    Incomplete,
+    Output(String),
+    Error(String),
+    Quit,
+}
+
+pub struct Repl {
+    // Lines of an incomplete input.
+    buffer: String,
+    last: Option<String>,
+    style: Style,
+    history: History,
+}
+
+impl Repl {
+    pub fn new(style: Style) -> Repl {
+        Repl::with_history(style, History::default())
+    }
+    pub fn with_history(style: Style, history: History) -> Repl {
+        Repl {
+            buffer: String::new(),
+            last: None,
+            style,
+            history,
+        }
+    }
+    pub fn prompt(&self) -> &'static str {
+        if self.buffer.is_empty() {
+            "> "
+        } else {
+            "... "
+        }
+    }
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+    // Takes one line, without its line break.
+    pub fn feed(&mut self, line: &str) -> Reply {
+        if !self.buffer.is_empty() {
+            if line.trim() == ":break" {
+                self.buffer.clear();
+                return Reply::Output(String::new());
+            }
+            self.buffer.push('\n');
+        } else if line.trim().is_empty() {
+            return Reply::Output(String::new());
+        }
+        self.buffer.push_str(line);
+        let trimmed = self.buffer.trim_start();
+        if !trimmed.starts_with(':') && is_incomplete(&self.buffer) {
+            return Reply::Incomplete;
+        }
+        let input = std::mem::take(&mut self.buffer);
+        if let Err(e) = self.history.add(&input) {
+            return Reply::Error(format!("history: {}\n", e));
+        }
+        match input.trim().strip_prefix(':') {
+            Some(command) => self.command(command),
+            None => {
+                self.last = Some(input.clone());
+                self.show(&input)
+            }
+        }
+    }
+    fn command(&mut self, command: &str) -> Reply {
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+        match name {
+            "tokens" | "ast" | "json" => {
+                let code = if argument.is_empty() {
+                    match &self.last {
+                        Some(last) => last.clone(),
+                        None => return Reply::Error("nothing entered yet\n".to_owned()),
+                    }
+                } else {
+                    argument.to_owned()
+                };
+                match name {
+                    "tokens" => self.tokens(&code),
+                    "ast" => self.parse(&code).map_or_else(Reply::Error, |program| {
+                        Reply::Output(format!("{}\n", to_sexpr(&program)))
+                    }),
+                    _ => self.parse(&code).map_or_else(Reply::Error, |program| {
+                        let value = to_value(&program, OutputFormat::Estree, Some(&code));
+                        Reply::Output(
+                            serde_json::to_string_pretty(&value)
+                                .expect("JSON values are serializable")
+                                + "\n",
+                        )
+                    }),
+                }
+            }
+            "load" if !argument.is_empty() => match fs::read_to_string(argument) {
+                Ok(source) => {
+                    self.last = Some(source.clone());
+                    self.show(&source)
+                }
+                Err(e) => Reply::Error(format!("{}: {}\n", argument, e)),
+            },
+            "history" => Reply::Output(
+                self.history
+                    .entries()
+                    .iter()
+                    .map(|entry| format!("{}\n", entry))
+                    .collect(),
+            ),
+            "break" => Reply::Output(String::new()),
+            "help" => Reply::Output(HELP.to_owned()),
+            "quit" | "exit" => Reply::Quit,
+            _ => Reply::Error(format!("unknown command :{}, see :help\n", command)),
+        }
+    }
+    // There is no evaluator yet, an input shows its AST.
+    fn show(&self, source: &str) -> Reply {
+        self.parse(source).map_or_else(Reply::Error, |program| {
+            Reply::Output(format!("{}\n", to_sexpr(&program)))
+        })
+    }
+    fn tokens(&self, source: &str) -> Reply {
+        let stream = collect(ParserFactory::tokenizer(source.to_owned()), source, false);
+        match &stream.error {
+            Some(e) => Reply::Error(self.render(e, source)),
+            None => Reply::Output(dump(&stream, &DumpOptions::default())),
+        }
+    }
+    // Inputs may leave out the last `;`.
+    fn parse(&self, source: &str) -> Result<Program, String> {
+        let options = ParseOptions {
+            asi: true,
+            ..ParseOptions::default()
+        };
+        ParserFactory::create(source.to_owned())
+            .with_options(options)
+            .parse()
+            .map_err(|e| self.render(&e, source))
+    }
+    fn render(&self, error: &DomainError, source: &str) -> String {
+        render(&error.to_diagnostic(), NAME, source, self.style)
+    }
+}
+
+// Whether `source` stops inside a string, a comment or brackets, so the
+// input goes on on the next line.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0usize;
+    let mut characters = source.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' | '\'' if !characters.by_ref().any(|next| next == character) => return true,
+            '/' if characters.peek() == Some(&'/') => {
+                characters.find(|next| *next == '\n');
+            }
+            '/' if characters.peek() == Some(&'*') => {
+                characters.next();
+                let mut previous = ' ';
+                let closed = characters.by_ref().any(|next| {
+                    let end = previous == '*' && next == '/';
+                    previous = next;
+                    end
+                });
+                if !closed {
+                    return true;
+                }
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+// Inputs entered so far, one JSON string per line in the file so that
+// multi-line inputs stay whole.
+#[derive(Debug, Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    entries: Vec<String>,
+}
+
+impl History {
+    // History kept in `path`, which is created on the first input.
+    pub fn load(path: impl AsRef<Path>) -> Result<History, DomainError> {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(DomainError::Io(format!("{}: {}", path.display(), e))),
+        };
+        let entries = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        Ok(History {
+            path: Some(path.to_owned()),
+            entries,
+        })
+    }
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+    fn add(&mut self, entry: &str) -> io::Result<()> {
+        self.entries.push(entry.to_owned());
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let line = serde_json::to_string(entry).expect("strings are serializable");
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn output(reply: Reply) -> String {
+        match reply {
+            Reply::Output(output) => output,
+            reply => panic!("expected an output, got {:?}", reply),
+        }
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(!is_incomplete("1; 'a';"));
+        assert!(is_incomplete("'abc"));
+        assert!(is_incomplete("1; /* comment"));
+        assert!(!is_incomplete("1; /* c */ // 'x\n2;"));
+        assert!(is_incomplete("{ ("));
+        assert!(!is_incomplete("{ ( ) } )"));
+        assert!(!is_incomplete("\"it's\""));
+    }
+
+    #[test]
+    fn test_multi_line_input() {
+        let mut repl = Repl::new(Style::Plain);
+        assert_eq!(repl.prompt(), "> ");
+        assert_eq!(output(repl.feed("42")), "(program (expr-stmt 42))\n");
+        assert_eq!(repl.feed("1; 'two"), Reply::Incomplete);
+        assert_eq!(repl.prompt(), "... ");
+        assert_eq!(
+            output(repl.feed("lines';")),
+            "(program (expr-stmt 1) (expr-stmt \"two\\nlines\"))\n"
+        );
+        assert_eq!(repl.prompt(), "> ");
+        assert_eq!(output(repl.feed("")), "");
+        assert!(matches!(repl.feed("1 2"), Reply::Error(e) if e.starts_with("error[E0002]")));
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut repl = Repl::new(Style::Plain);
+        assert!(matches!(repl.feed(":ast"), Reply::Error(_)));
+        repl.feed("7;");
+        assert_eq!(
+            output(repl.feed(":tokens")),
+            "SPAN  KIND       TEXT\n0..1  Number     7\n1..2  SemiColon  ;\n"
+        );
+        assert_eq!(
+            output(repl.feed(":ast 'a'")),
+            "(program (expr-stmt \"a\"))\n"
+        );
+        assert!(output(repl.feed(":json")).contains("\"type\": \"Program\""));
+        assert_eq!(repl.feed("'open"), Reply::Incomplete);
+        assert_eq!(output(repl.feed(":break")), "");
+        assert_eq!(repl.prompt(), "> ");
+        assert!(matches!(repl.feed(":nope"), Reply::Error(_)));
+        assert_eq!(repl.feed(":quit"), Reply::Quit);
+    }
+
+    #[test]
+    fn test_load_and_history() {
+        let directory = env::temp_dir().join(format!("repl-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let script = directory.join("script.js");
+        fs::write(&script, "1;\n'loaded';\n").unwrap();
+        let path = directory.join("history");
+
+        let mut repl = Repl::with_history(Style::Plain, History::load(&path).unwrap());
+        assert_eq!(
+            output(repl.feed(&format!(":load {}", script.display()))),
+            "(program (expr-stmt 1) (expr-stmt \"loaded\"))\n"
+        );
+        repl.feed("'a");
+        repl.feed("b';");
+        let reloaded = History::load(&path).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(reloaded.entries(), repl.history().entries());
+        assert_eq!(reloaded.entries()[1], "'a\nb';");
+    }
+}
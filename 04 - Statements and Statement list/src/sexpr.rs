@@ -0,0 +1,80 @@
+// Compact S-expression form of a `Program`, one line with no spans, e.g.
+// `(program (expr-stmt 42) (expr-stmt "a"))`. Literals are atoms, strings
+// quoted as in JSON.
+
+use crate::{
+    models::Program,
+    visit::{NodeRef, Visit, VisitControl},
+};
+
+pub fn to_sexpr(program: &Program) -> String {
+    node_to_sexpr(NodeRef::Program(program))
+}
+
+pub fn node_to_sexpr(node: NodeRef<'_>) -> String {
+    let mut printer = Sexpr {
+        output: String::new(),
+    };
+    match node {
+        NodeRef::Program(program) => printer.visit_program(program),
+        NodeRef::Statement(statement) => printer.visit_statement(statement),
+        NodeRef::ExpressionStatement(statement) => printer.visit_expression_statement(statement),
+        NodeRef::Expression(expression) => printer.visit_expression(expression),
+        NodeRef::Literal(literal) => printer.visit_literal(literal),
+        NodeRef::NumericLiteral(literal) => printer.visit_numeric_literal(literal),
+        NodeRef::StringLiteral(literal) => printer.visit_string_literal(literal),
+    }
+    printer.output
+}
+
+struct Sexpr {
+    output: String,
+}
+
+impl Sexpr {
+    fn push(&mut self, text: &str) {
+        if !self.output.is_empty() && !self.output.ends_with('(') {
+            self.output.push(' ');
+        }
+        self.output.push_str(text);
+    }
+}
+
+// The `Statement`, `Expression` and `Literal` enums only wrap the node they
+// hold and don't show up.
+impl Visit for Sexpr {
+    fn enter(&mut self, node: NodeRef<'_>) -> VisitControl {
+        match node {
+            NodeRef::Program(_) => self.push("(program"),
+            NodeRef::ExpressionStatement(_) => self.push("(expr-stmt"),
+            NodeRef::NumericLiteral(literal) => self.push(&literal.value.to_string()),
+            NodeRef::StringLiteral(literal) => {
+                self.push(&serde_json::to_string(&literal.value).expect("strings are serializable"))
+            }
+            NodeRef::Statement(_) | NodeRef::Expression(_) | NodeRef::Literal(_) => {}
+        }
+        VisitControl::Continue
+    }
+    fn leave(&mut self, node: NodeRef<'_>) {
+        if matches!(node, NodeRef::Program(_) | NodeRef::ExpressionStatement(_)) {
+            self.output.push(')');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_factory::ParserFactory;
+
+    #[test]
+    fn test_to_sexpr() {
+        let program = ParserFactory::create("42; 'a \"b\"';;\n// c\n'';".to_owned())
+            .parse()
+            .unwrap();
+        assert_eq!(
+            to_sexpr(&program),
+            r#"(program (expr-stmt 42) (expr-stmt "a \"b\"") (expr-stmt ""))"#
+        );
+    }
+}
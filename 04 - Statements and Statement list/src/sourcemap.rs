@@ -0,0 +1,393 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{codegen::Printed, models::DomainError};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Source Map revision 3, serializes to the JSON read by browsers and tools.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMap {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sources_content: Option<Vec<Option<String>>>,
+    // The grammar has no identifiers yet, so no mapping carries a name.
+    pub names: Vec<String>,
+    pub mappings: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceMapOptions {
+    // Name of the generated file.
+    pub file: Option<String>,
+    // Name of the parsed file, listed in `sources`.
+    pub source: String,
+    // Embeds the parsed text so the map is usable without the file.
+    pub sources_content: bool,
+}
+
+// One decoded segment, lines and columns are zero based and columns count
+// UTF-16 code units as the format requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedMapping {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    pub source: usize,
+    pub original_line: usize,
+    pub original_column: usize,
+    pub name: Option<usize>,
+}
+
+impl SourceMap {
+    // `original` is the text the printed program was parsed from.
+    pub fn new(original: &str, printed: &Printed, options: &SourceMapOptions) -> SourceMap {
+        let original_lines = LineIndex::new(original);
+        let generated_lines = LineIndex::new(&printed.code);
+        let mut mappings = String::new();
+        let mut line = 0;
+        let mut previous = [0i64; 4];
+        for mapping in printed.mappings.iter() {
+            let (generated_line, generated_column) = generated_lines.line_col(mapping.generated);
+            let (original_line, original_column) = original_lines.line_col(mapping.original);
+            if generated_line > line || mappings.is_empty() {
+                while line < generated_line {
+                    mappings.push(';');
+                    line += 1;
+                }
+                previous[0] = 0;
+            } else {
+                mappings.push(',');
+            }
+            let segment = [
+                generated_column as i64,
+                0,
+                original_line as i64,
+                original_column as i64,
+            ];
+            for (value, previous) in segment.iter().zip(previous.iter_mut()) {
+                vlq_encode(value - *previous, &mut mappings);
+                *previous = *value;
+            }
+        }
+        SourceMap {
+            version: 3,
+            file: options.file.clone(),
+            sources: vec![options.source.clone()],
+            sources_content: options
+                .sources_content
+                .then(|| vec![Some(original.to_owned())]),
+            names: Vec::new(),
+            mappings,
+        }
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("SourceMap is serializable")
+    }
+    pub fn decode(&self) -> Result<Vec<DecodedMapping>, DomainError> {
+        let mut decoded = Vec::new();
+        let mut previous = [0i64; 5];
+        for (generated_line, line) in self.mappings.split(';').enumerate() {
+            previous[0] = 0;
+            for segment in line.split(',').filter(|segment| !segment.is_empty()) {
+                let values = vlq_decode(segment)?;
+                if !matches!(values.len(), 1 | 4 | 5) {
+                    return Err(DomainError::InvalidSourceMap(format!(
+                        "segment {:?} has {} fields",
+                        segment,
+                        values.len()
+                    )));
+                }
+                for (value, previous) in values.iter().zip(previous.iter_mut()) {
+                    *previous += value;
+                    if *previous < 0 {
+                        return Err(DomainError::InvalidSourceMap(format!(
+                            "segment {:?} has a negative position",
+                            segment
+                        )));
+                    }
+                }
+                // Segments without a source position map to nothing.
+                if values.len() == 1 {
+                    continue;
+                }
+                decoded.push(DecodedMapping {
+                    generated_line,
+                    generated_column: previous[0] as usize,
+                    source: previous[1] as usize,
+                    original_line: previous[2] as usize,
+                    original_column: previous[3] as usize,
+                    name: (values.len() == 5).then_some(previous[4] as usize),
+                });
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+// Converts byte offsets to line and UTF-16 column, lines end at `\n`.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex<'_> {
+    pub fn new(text: &str) -> LineIndex<'_> {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        LineIndex { text, line_starts }
+    }
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let column = self.text[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (line, column)
+    }
+    // Inverse of `line_col`, `None` when the position is outside the text or
+    // inside a character.
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        let start = *self.line_starts.get(line)?;
+        let mut units = 0;
+        for (index, character) in self.text[start..].char_indices() {
+            if units == column {
+                return Some(start + index);
+            }
+            if units > column || character == '\n' {
+                return None;
+            }
+            units += character.len_utf16();
+        }
+        (units == column).then_some(self.text.len())
+    }
+}
+
+pub fn vlq_encode(value: i64, output: &mut String) {
+    let mut value = if value < 0 {
+        (value.unsigned_abs() << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (value & 0b11111) as usize;
+        value >>= 5;
+        if value > 0 {
+            digit |= 0b100000;
+        }
+        output.push(BASE64[digit] as char);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+pub fn vlq_decode(segment: &str) -> Result<Vec<i64>, DomainError> {
+    let mut values = Vec::new();
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    for character in segment.bytes() {
+        let digit = BASE64
+            .iter()
+            .position(|base64| *base64 == character)
+            .ok_or_else(|| {
+                DomainError::InvalidSourceMap(format!(
+                    "invalid base64 digit {:?}",
+                    character as char
+                ))
+            })? as u64;
+        if shift > 60 {
+            return Err(DomainError::InvalidSourceMap(format!(
+                "value too large in {:?}",
+                segment
+            )));
+        }
+        value |= (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+            continue;
+        }
+        let magnitude = (value >> 1) as i64;
+        values.push(if value & 1 == 1 {
+            -magnitude
+        } else {
+            magnitude
+        });
+        value = 0;
+        shift = 0;
+    }
+    if shift > 0 {
+        return Err(DomainError::InvalidSourceMap(format!(
+            "unterminated value in {:?}",
+            segment
+        )));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codegen::{print_with_mappings, CodegenOptions},
+        models::{
+            Expression, ExpressionStatement, Literal, NumericLiteral, Program, Span, Statement,
+        },
+        parser_factory::ParserFactory,
+        visit::{Fold, VisitMut},
+    };
+
+    fn options() -> SourceMapOptions {
+        SourceMapOptions {
+            file: Some("out.js".to_owned()),
+            source: "in.js".to_owned(),
+            sources_content: false,
+        }
+    }
+
+    fn parse(to_parse: &str) -> Program {
+        ParserFactory::create(to_parse.to_owned()).parse().unwrap()
+    }
+
+    // Every decoded mapping points the start of a printed node back to the
+    // start of the node it came from.
+    fn assert_round_trip(original: &str, program: &Program) {
+        let printed = print_with_mappings(program, &CodegenOptions::default()).unwrap();
+        let map = SourceMap::new(original, &printed, &options());
+        let read_back: SourceMap = serde_json::from_str(&map.to_json()).unwrap();
+        let decoded = read_back.decode().unwrap();
+        assert_eq!(decoded.len(), printed.mappings.len());
+        let generated_lines = LineIndex::new(&printed.code);
+        let original_lines = LineIndex::new(original);
+        for (decoded, mapping) in decoded.iter().zip(printed.mappings.iter()) {
+            assert_eq!(
+                generated_lines.offset(decoded.generated_line, decoded.generated_column),
+                Some(mapping.generated)
+            );
+            assert_eq!(
+                original_lines.offset(decoded.original_line, decoded.original_column),
+                Some(mapping.original)
+            );
+            assert_eq!(decoded.source, 0);
+            assert_eq!(decoded.name, None);
+        }
+    }
+
+    #[test]
+    fn test_vlq() {
+        let mut encoded = String::new();
+        for value in [
+            0,
+            1,
+            -1,
+            15,
+            16,
+            -16,
+            1024,
+            i32::MAX as i64,
+            -(i32::MAX as i64),
+        ] {
+            vlq_encode(value, &mut encoded);
+            encoded.push(',');
+        }
+        assert!(encoded.starts_with("A,C,D,e,gB,hB,ggC,"));
+        let decoded: Vec<i64> = encoded
+            .split(',')
+            .filter(|segment| !segment.is_empty())
+            .flat_map(|segment| vlq_decode(segment).unwrap())
+            .collect();
+        assert_eq!(
+            decoded,
+            [
+                0,
+                1,
+                -1,
+                15,
+                16,
+                -16,
+                1024,
+                i32::MAX as i64,
+                -(i32::MAX as i64)
+            ]
+        );
+        assert!(vlq_decode("g").is_err());
+        assert!(vlq_decode("!").is_err());
+    }
+
+    #[test]
+    fn test_mappings() {
+        let original = "  42;\n'a';  'héllo';\n\n7;";
+        let printed = print_with_mappings(&parse(original), &CodegenOptions::default()).unwrap();
+        let map = SourceMap::new(original, &printed, &options());
+        assert_eq!(printed.code, "42;\n\"a\";\n\"héllo\";\n7;\n");
+        assert_eq!(map.mappings, "AAAE;AACF;AAAM;AAEN");
+        assert_eq!(
+            map.decode().unwrap()[2],
+            DecodedMapping {
+                generated_line: 2,
+                generated_column: 0,
+                source: 0,
+                original_line: 1,
+                original_column: 6,
+                name: None,
+            }
+        );
+        assert_round_trip(original, &parse(original));
+    }
+
+    #[test]
+    fn test_transformed_program() {
+        struct Increment;
+        impl VisitMut for Increment {
+            fn visit_numeric_literal_mut(&mut self, literal: &mut NumericLiteral) {
+                literal.value += 1000;
+            }
+        }
+        // Synthesized nodes have no span and are left unmapped.
+        struct Prepend;
+        impl Fold for Prepend {
+            fn fold_program(&mut self, mut program: Program) -> Program {
+                let literal = NumericLiteral {
+                    value: 0,
+                    span: Span::default(),
+                };
+                program.body.insert(
+                    0,
+                    Statement::Expression(ExpressionStatement {
+                        expression: Expression::Literal(Literal::Numeric(literal)),
+                        span: Span::default(),
+                    }),
+                );
+                program
+            }
+        }
+        let original = "1;\n  'é';  2;\n";
+        let mut program = parse(original);
+        Increment.visit_program_mut(&mut program);
+        let program = Prepend.fold_program(program);
+        let printed = print_with_mappings(&program, &CodegenOptions::default()).unwrap();
+        assert_eq!(printed.code, "0;\n1001;\n\"é\";\n1002;\n");
+        assert_eq!(printed.mappings.len(), 3);
+        assert_round_trip(original, &program);
+    }
+
+    #[test]
+    fn test_sources_content() {
+        let original = "1;";
+        let printed = print_with_mappings(&parse(original), &CodegenOptions::default()).unwrap();
+        assert_eq!(
+            SourceMap::new(original, &printed, &options()).to_json(),
+            r#"{"version":3,"file":"out.js","sources":["in.js"],"names":[],"mappings":"AAAA"}"#
+        );
+        let embedded = SourceMapOptions {
+            sources_content: true,
+            ..options()
+        };
+        assert_eq!(
+            SourceMap::new(original, &printed, &embedded).sources_content,
+            Some(vec![Some("1;".to_owned())])
+        );
+    }
+}
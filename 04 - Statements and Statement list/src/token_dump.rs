@@ -0,0 +1,326 @@
+// Token streams printed for debugging specs: every token with its kind, raw
+// text and span, and optionally the skipped trivia.
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    models::{DomainError, ParsedValue, Span},
+    tokenizer::{TokenType, Tokenizer},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DumpFormat {
+    // Aligned columns with a header.
+    #[default]
+    Table,
+    // One JSON object per token.
+    JsonLines,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DumpOptions {
+    pub format: DumpFormat,
+    // Kinds to print, all of them when empty. "Trivia" names skipped text.
+    pub kinds: Vec<String>,
+    pub trivia: bool,
+}
+
+impl DumpOptions {
+    pub fn validate(&self) -> Result<(), DomainError> {
+        match self
+            .kinds
+            .iter()
+            .find(|kind| *kind != "Trivia" && TokenType::from_name(kind).is_none())
+        {
+            Some(kind) => Err(DomainError::UnknownTokenKind(kind.clone())),
+            None => Ok(()),
+        }
+    }
+    fn shows(&self, token: &DumpedToken) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&token.kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DumpedToken {
+    pub kind: String,
+    pub text: String,
+    pub span: Span,
+}
+
+// What a tokenizer produced from its input, up to the error that stopped it
+// if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenStream {
+    pub tokens: Vec<DumpedToken>,
+    pub error: Option<DomainError>,
+}
+
+// Runs `tokenizer` over `source`, the input it was built with.
+pub fn collect(
+    tokenizer: Tokenizer<TokenType, ParsedValue>,
+    source: &str,
+    trivia: bool,
+) -> TokenStream {
+    let mut tokenizer = if trivia {
+        tokenizer.with_trivia()
+    } else {
+        tokenizer
+    };
+    let mut tokens = Vec::new();
+    let dumped = |kind: String, span: Span| DumpedToken {
+        kind,
+        text: source[span.start..span.end].to_owned(),
+        span,
+    };
+    let error = loop {
+        let next = tokenizer.get_next_token();
+        for span in tokenizer.take_trivia() {
+            tokens.push(dumped("Trivia".to_owned(), span));
+        }
+        match next {
+            Ok(Some(token)) => tokens.push(dumped(format!("{:?}", token.token_type), token.span)),
+            Ok(None) => break None,
+            Err(e) => break Some(e),
+        }
+    };
+    TokenStream { tokens, error }
+}
+
+pub fn dump(stream: &TokenStream, options: &DumpOptions) -> String {
+    let rows: Vec<(&str, &DumpedToken)> = stream
+        .tokens
+        .iter()
+        .filter(|token| options.shows(token))
+        .map(|token| ("", token))
+        .collect();
+    render(&rows, options.format)
+}
+
+// The tokens of `new` that differ from `old`, as removed (`-`) and added
+// (`+`) rows. Both streams come from the same input, so tokens are matched
+// by position and the diff takes a single pass.
+pub fn diff(old: &TokenStream, new: &TokenStream, options: &DumpOptions) -> String {
+    let old_tokens: Vec<&DumpedToken> = old
+        .tokens
+        .iter()
+        .filter(|token| options.shows(token))
+        .collect();
+    let new_tokens: Vec<&DumpedToken> = new
+        .tokens
+        .iter()
+        .filter(|token| options.shows(token))
+        .collect();
+    let mut rows = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_tokens.len() || j < new_tokens.len() {
+        match (old_tokens.get(i), new_tokens.get(j)) {
+            (Some(removed), Some(added)) if removed == added => {
+                i += 1;
+                j += 1;
+            }
+            (Some(removed), Some(added)) if added.span.start < removed.span.start => {
+                rows.push(("+", *added));
+                j += 1;
+            }
+            (Some(removed), _) => {
+                rows.push(("-", *removed));
+                i += 1;
+            }
+            (None, Some(added)) => {
+                rows.push(("+", *added));
+                j += 1;
+            }
+            (None, None) => unreachable!("the loop stops when both are done"),
+        }
+    }
+    let mut output = render(&rows, options.format);
+    if old.error != new.error {
+        for (change, error) in [("-", &old.error), ("+", &new.error)] {
+            if let Some(error) = error {
+                let message = error.to_diagnostic().message;
+                output.push_str(&match options.format {
+                    DumpFormat::Table => format!("{} error: {}\n", change, message),
+                    DumpFormat::JsonLines => {
+                        json!({ "change": change, "error": message }).to_string() + "\n"
+                    }
+                });
+            }
+        }
+    }
+    output
+}
+
+// Rows are prefixed by their change, an empty change leaves the prefix out.
+fn render(rows: &[(&str, &DumpedToken)], format: DumpFormat) -> String {
+    let mut output = String::new();
+    match format {
+        DumpFormat::Table if rows.is_empty() => {}
+        DumpFormat::Table => {
+            let diff = rows.iter().any(|(change, _)| !change.is_empty());
+            let spans: Vec<String> = rows
+                .iter()
+                .map(|(_, token)| format!("{}..{}", token.span.start, token.span.end))
+                .collect();
+            let span_width = spans.iter().map(String::len).fold(4, usize::max);
+            let kind_width = rows
+                .iter()
+                .map(|(_, token)| token.kind.len())
+                .fold(4, usize::max);
+            let prefix = if diff { "  " } else { "" };
+            output.push_str(&format!(
+                "{}{:<span_width$}  {:<kind_width$}  TEXT\n",
+                prefix, "SPAN", "KIND"
+            ));
+            for ((change, token), span) in rows.iter().zip(spans) {
+                let prefix = if diff {
+                    format!("{} ", change)
+                } else {
+                    String::new()
+                };
+                output.push_str(&format!(
+                    "{}{:<span_width$}  {:<kind_width$}  {}\n",
+                    prefix,
+                    span,
+                    token.kind,
+                    escape(&token.text)
+                ));
+            }
+        }
+        DumpFormat::JsonLines => {
+            for (change, token) in rows {
+                let mut value = json!({
+                    "kind": token.kind,
+                    "text": token.text,
+                    "start": token.span.start,
+                    "end": token.span.end,
+                });
+                if !change.is_empty() {
+                    value["change"] = json!(change);
+                }
+                output.push_str(&value.to_string());
+                output.push('\n');
+            }
+        }
+    }
+    output
+}
+
+// Keeps each row on one line.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|character| {
+            if character.is_control() {
+                character.escape_default().to_string()
+            } else {
+                character.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+
+    use super::*;
+    use crate::parser_factory::ParserFactory;
+
+    fn stream(source: &str, trivia: bool) -> TokenStream {
+        collect(ParserFactory::tokenizer(source.to_owned()), source, trivia)
+    }
+
+    #[test]
+    fn test_table() {
+        let options = DumpOptions::default();
+        assert_eq!(
+            dump(&stream("42;\n'a\tb';", false), &options),
+            "\
+SPAN   KIND       TEXT
+0..2   Number     42
+2..3   SemiColon  ;
+4..9   String     'a\\tb'
+9..10  SemiColon  ;
+"
+        );
+    }
+
+    #[test]
+    fn test_trivia_filter_and_json_lines() {
+        let options = DumpOptions {
+            format: DumpFormat::JsonLines,
+            kinds: vec!["Trivia".to_owned()],
+            trivia: true,
+        };
+        assert_eq!(
+            dump(&stream("1; // one\n2;", true), &options),
+            "\
+{\"end\":3,\"kind\":\"Trivia\",\"start\":2,\"text\":\" \"}
+{\"end\":9,\"kind\":\"Trivia\",\"start\":3,\"text\":\"// one\"}
+{\"end\":10,\"kind\":\"Trivia\",\"start\":9,\"text\":\"\\n\"}
+"
+        );
+        assert!(DumpOptions {
+            kinds: vec!["Comment".to_owned()],
+            ..DumpOptions::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let source = "12;; 'a';";
+        let old = stream(source, false);
+        // Numbers limited to one digit, and a single `;` per token.
+        let spec = indexmap! {
+            r"^\s+".to_owned() => TokenType::Null,
+            r"^;".to_owned() => TokenType::SemiColon,
+            r"^\d".to_owned() => TokenType::Number,
+            r"^'[^']*'".to_owned() => TokenType::String,
+        };
+        let new = collect(
+            Tokenizer::new(TokenType::rules(spec).unwrap(), source.to_owned()),
+            source,
+            false,
+        );
+        assert_eq!(
+            diff(&old, &new, &DumpOptions::default()),
+            "  SPAN  KIND       TEXT
+- 0..2  Number     12
++ 0..1  Number     1
++ 1..2  Number     2
+- 2..4  SemiColon  ;;
++ 2..3  SemiColon  ;
++ 3..4  SemiColon  ;
+"
+        );
+        assert_eq!(diff(&old, &old, &DumpOptions::default()), "");
+    }
+
+    #[test]
+    fn test_diff_errors() {
+        let source = "1; 'a';";
+        let old = stream(source, false);
+        // No rule for strings.
+        let spec = indexmap! {
+            r"^\s+".to_owned() => TokenType::Null,
+            r"^;".to_owned() => TokenType::SemiColon,
+            r"^\d+".to_owned() => TokenType::Number,
+        };
+        let new = collect(
+            Tokenizer::new(TokenType::rules(spec).unwrap(), source.to_owned()),
+            source,
+            false,
+        );
+        assert_eq!(
+            diff(&old, &new, &DumpOptions::default()),
+            "  SPAN  KIND       TEXT
+- 3..6  String     'a'
+- 6..7  SemiColon  ;
++ error: unexpected character `'`
+"
+        );
+    }
+}
@@ -1,10 +1,14 @@
-use std::collections::{BTreeMap, HashMap};
+use std::io::{ErrorKind, Read};
+
 use indexmap::IndexMap;
 
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::models::ParsedValue;
+use crate::models::{DomainError, ParsedValue, Span};
+
+// Token kinds of the JavaScript subset handled by `Parser`, `Null` marks the
+// rules whose matches are skipped.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenType {
     Null,
@@ -12,63 +16,711 @@ pub enum TokenType {
     Number,
     String,
 }
+pub type TokenValue = Token<TokenType, ParsedValue>;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct TokenValue {
-    pub token_type: TokenType,
-    pub value: ParsedValue,
+pub struct Token<K, V> {
+    pub token_type: K,
+    pub value: V,
+    pub span: Span,
+}
+
+// Where `Parser` reads its tokens from, implemented by `Tokenizer` and by
+// the generated `DfaTokenizer`.
+pub trait TokenSource {
+    fn get_next_token(&mut self) -> Result<Option<TokenValue>, DomainError>;
+    // Offset in the whole input of the next character to tokenize.
+    fn position(&self) -> usize;
+    // Whether the input skipped before the last token holds a line break.
+    fn newline_before(&self) -> bool;
+    // Starts keeping the spans of skipped matches for `take_trivia`.
+    fn keep_trivia(&mut self);
+    // Skipped matches since the last call.
+    fn take_trivia(&mut self) -> Vec<Span>;
+    fn warnings(&self) -> &[SpecWarning] {
+        &[]
+    }
+}
+
+// Turns the matched text of a rule into the token value.
+pub type Convert<V> = Box<dyn Fn(&str) -> Result<V, DomainError>>;
+
+// How `search_token` picks between rules that match at the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchPolicy {
+    // The longest match wins; rules earlier in the spec win ties.
+    #[default]
+    LongestMatch,
+    // The first rule in spec order that matches wins.
+    FirstMatch,
+}
+
+// Problems found in the spec when the tokenizer is built.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpecWarning {
+    // The regex does not start with `^`, it is matched at the cursor anyway.
+    MissingAnchor { pattern: String },
+    // The regex accepts the empty string, such matches are ignored.
+    MatchesEmpty { pattern: String },
+    // An earlier rule has the same regex once the anchor is ignored, or this
+    // rule is a literal that an earlier rule matches in full, so this one can
+    // never win.
+    Unreachable { pattern: String, shadowed_by: usize },
+}
+
+pub struct Rule<K, V> {
+    pattern: String,
+    regex: Regex,
+    // `None` for rules whose matches are skipped.
+    token: Option<(K, Convert<V>)>,
 }
-pub struct Tokenizer {
-    to_parse: String,
+
+impl<K, V> Rule<K, V> {
+    pub fn token(
+        pattern: &str,
+        token_type: K,
+        convert: impl Fn(&str) -> Result<V, DomainError> + 'static,
+    ) -> Result<Rule<K, V>, DomainError> {
+        Ok(Rule {
+            pattern: pattern.to_owned(),
+            regex: compile(pattern)?,
+            token: Some((token_type, Box::new(convert))),
+        })
+    }
+    pub fn skip(pattern: &str) -> Result<Rule<K, V>, DomainError> {
+        Ok(Rule {
+            pattern: pattern.to_owned(),
+            regex: compile(pattern)?,
+            token: None,
+        })
+    }
+}
+
+// Rules only match at the cursor. The regex is anchored so that a search
+// stops there instead of scanning the rest of the input, errors are about
+// the pattern as written.
+fn compile(pattern: &str) -> Result<Regex, DomainError> {
+    let invalid = |e: regex::Error| DomainError::InvalidRegex {
+        regex: pattern.to_owned(),
+        message: e.to_string(),
+    };
+    Regex::new(pattern).map_err(invalid)?;
+    Regex::new(&format!("^(?:{})", pattern)).map_err(invalid)
+}
+
+impl TokenType {
+    // The kinds a rule can produce, `Null` is left out since it only skips.
+    pub const KINDS: [TokenType; 3] = [TokenType::SemiColon, TokenType::Number, TokenType::String];
+
+    pub fn from_name(name: &str) -> Option<TokenType> {
+        Self::KINDS
+            .into_iter()
+            .find(|kind| format!("{:?}", kind) == name)
+    }
+    // Builds the rules of a spec where every regex maps to a `TokenType`.
+    pub fn rules(
+        spec: IndexMap<String, TokenType>,
+    ) -> Result<Vec<Rule<TokenType, ParsedValue>>, DomainError> {
+        spec.into_iter()
+            .map(|(pattern, token_type)| match token_type {
+                TokenType::Null => Rule::skip(&pattern),
+                _ => Rule::token(&pattern, token_type.clone(), move |value| {
+                    token_type.convert(value)
+                }),
+            })
+            .collect()
+    }
+    pub fn convert(&self, value: &str) -> Result<ParsedValue, DomainError> {
+        match self {
+            TokenType::Number => Ok(ParsedValue::Number(value.parse::<u128>()?)),
+            _ => Ok(ParsedValue::String(value.to_owned())),
+        }
+    }
+}
+
+// Bytes read from a streaming source at a time. It is also the lookahead kept
+// past the cursor, so a token is only cut short by a chunk boundary when it
+// is longer than this and a shorter match is possible.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct Tokenizer<K, V> {
+    // Window of the input, `offset` is its position in the whole input.
+    buffer: String,
+    offset: usize,
     cursor: usize,
-    spec: IndexMap<String, TokenType>,
+    reader: Option<Box<dyn Read>>,
+    // Trailing bytes of an incomplete UTF-8 sequence.
+    pending: Vec<u8>,
+    chunk_size: usize,
+    finished: bool,
+    rules: Vec<Rule<K, V>>,
+    policy: MatchPolicy,
+    warnings: Vec<SpecWarning>,
+    newline_before: bool,
+    // Spans of the skipped matches, when asked for.
+    trivia: Option<Vec<Span>>,
 }
 
-impl Tokenizer {
-    pub fn new(spec: IndexMap<String, TokenType>, to_parse: String) -> Tokenizer {
+impl<K: Clone, V> Tokenizer<K, V> {
+    pub fn new(rules: Vec<Rule<K, V>>, to_parse: String) -> Tokenizer<K, V> {
+        Tokenizer::with_policy(rules, to_parse, MatchPolicy::default())
+    }
+    pub fn with_policy(
+        rules: Vec<Rule<K, V>>,
+        to_parse: String,
+        policy: MatchPolicy,
+    ) -> Tokenizer<K, V> {
+        let warnings = validate(&rules);
         Tokenizer {
-            to_parse,
+            buffer: to_parse,
+            offset: 0,
             cursor: 0,
-            spec,
+            reader: None,
+            pending: vec![],
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            finished: false,
+            rules,
+            policy,
+            warnings,
+            newline_before: false,
+            trivia: None,
         }
     }
+    // Tokenizes the input as it is read, keeping about two chunks in memory.
+    pub fn from_reader(rules: Vec<Rule<K, V>>, reader: impl Read + 'static) -> Tokenizer<K, V> {
+        let mut tokenizer = Tokenizer::new(rules, String::new());
+        tokenizer.reader = Some(Box::new(reader));
+        tokenizer
+    }
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Tokenizer<K, V> {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+    // Keeps the spans of skipped matches for `take_trivia`.
+    pub fn with_trivia(mut self) -> Tokenizer<K, V> {
+        self.trivia = Some(vec![]);
+        self
+    }
+    // Skipped matches since the last call, the ones before the last token
+    // when called after `get_next_token`.
+    pub fn take_trivia(&mut self) -> Vec<Span> {
+        self.trivia.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+    pub fn set_policy(&mut self, policy: MatchPolicy) {
+        self.policy = policy;
+    }
+    pub fn warnings(&self) -> &[SpecWarning] {
+        &self.warnings
+    }
+    // Offset in the whole input of the next character to tokenize.
+    pub fn position(&self) -> usize {
+        self.offset + self.cursor
+    }
     fn has_more_tokens(&self) -> bool {
-        self.cursor < self.to_parse.len()
+        self.cursor < self.buffer.len()
+    }
+    // Whether the input skipped before the last token holds a line break,
+    // which allows a semicolon to be inserted there.
+    pub fn newline_before(&self) -> bool {
+        self.newline_before
     }
-    pub fn get_next_token(&mut self) -> Option<TokenValue> {
-        if !self.has_more_tokens() {
-            return None;
+    pub fn get_next_token(&mut self) -> Result<Option<Token<K, V>>, DomainError> {
+        self.newline_before = false;
+        if self.finished {
+            return Ok(None);
         }
-        let string_data = self.to_parse[self.cursor..].to_string();
-        if let Some(token) = self.search_token(&string_data) {
-            self.cursor += token.0.len();
-            if token.1 == TokenType::Null {
-                return self.get_next_token();
+        let token = self.scan();
+        if !matches!(token, Ok(Some(_))) {
+            self.finished = true;
+        }
+        token
+    }
+    fn scan(&mut self) -> Result<Option<Token<K, V>>, DomainError> {
+        loop {
+            if self.buffer.len() - self.cursor < self.chunk_size && self.fill()? {
+                continue;
             }
-            return Some(TokenValue {
-                token_type: token.1,
-                value: self.parse(&token.0),
-            });
+            if !self.has_more_tokens() {
+                return Ok(None);
+            }
+            let found = self.search_token(&self.buffer[self.cursor..]);
+            // The match may go on in the part of the input not read yet.
+            let at_end = found.is_none_or(|(length, _)| self.cursor + length == self.buffer.len());
+            if at_end && self.fill()? {
+                continue;
+            }
+            let Some((length, index)) = found else {
+                return Err(DomainError::UnexpectedCharacter {
+                    character: self.buffer[self.cursor..].chars().next().unwrap(),
+                    offset: self.offset + self.cursor,
+                });
+            };
+            let span = Span::new(
+                self.offset + self.cursor,
+                self.offset + self.cursor + length,
+            );
+            let value = &self.buffer[self.cursor..self.cursor + length];
+            self.cursor += length;
+            match &self.rules[index].token {
+                Some((token_type, convert)) => {
+                    return Ok(Some(Token {
+                        token_type: token_type.clone(),
+                        value: convert(value).map_err(|e| e.at(span))?,
+                        span,
+                    }))
+                }
+                None => {
+                    self.newline_before |= value.contains(['\n', '\r', '\u{2028}', '\u{2029}']);
+                    if let Some(trivia) = self.trivia.as_mut() {
+                        trivia.push(span);
+                    }
+                }
+            }
+        }
+    }
+    // Appends the next chunk of the reader to the buffer, dropping what was
+    // already consumed. Returns false once the reader is exhausted.
+    fn fill(&mut self) -> Result<bool, DomainError> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(false);
+        };
+        let mut chunk = vec![0; self.chunk_size];
+        let read = loop {
+            match reader.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(DomainError::Io(e.to_string())),
+            }
+        };
+        if read == 0 {
+            self.reader = None;
+            if !self.pending.is_empty() {
+                return Err(DomainError::InvalidUtf8 {
+                    offset: self.offset + self.buffer.len(),
+                });
+            }
+            return Ok(false);
         }
-        None
+        self.buffer.drain(..self.cursor);
+        self.offset += self.cursor;
+        self.cursor = 0;
+        self.pending.extend_from_slice(&chunk[..read]);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(valid) => valid.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => {
+                return Err(DomainError::InvalidUtf8 {
+                    offset: self.offset + self.buffer.len() + e.valid_up_to(),
+                })
+            }
+        };
+        let bytes: Vec<u8> = self.pending.drain(..valid).collect();
+        self.buffer
+            .push_str(std::str::from_utf8(&bytes).expect("checked above"));
+        Ok(true)
     }
-    fn search_token(&self, string_data: &str) -> Option<(String, TokenType)> {
-        for (key, value) in self.spec.iter() {
-            if let Some(token) = self.match_token(key, &string_data) {
-                return Some((token, value.clone()));
+    // Returns the length of the match and the index of the rule that won.
+    fn search_token(&self, string_data: &str) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        for (index, rule) in self.rules.iter().enumerate() {
+            if let Some(length) = match_token(&rule.regex, string_data) {
+                if self.policy == MatchPolicy::FirstMatch {
+                    return Some((length, index));
+                }
+                if best.is_none_or(|(longest, _)| length > longest) {
+                    best = Some((length, index));
+                }
             }
         }
-        None
+        best
+    }
+}
+
+impl TokenSource for Tokenizer<TokenType, ParsedValue> {
+    fn get_next_token(&mut self) -> Result<Option<TokenValue>, DomainError> {
+        Tokenizer::get_next_token(self)
+    }
+    fn position(&self) -> usize {
+        Tokenizer::position(self)
+    }
+    fn newline_before(&self) -> bool {
+        Tokenizer::newline_before(self)
+    }
+    fn keep_trivia(&mut self) {
+        self.trivia.get_or_insert_with(Vec::new);
+    }
+    fn take_trivia(&mut self) -> Vec<Span> {
+        Tokenizer::take_trivia(self)
+    }
+    fn warnings(&self) -> &[SpecWarning] {
+        Tokenizer::warnings(self)
+    }
+}
+
+impl<K: Clone, V> Iterator for Tokenizer<K, V> {
+    type Item = Result<Token<K, V>, DomainError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.get_next_token().transpose()
+    }
+}
+
+fn validate<K, V>(rules: &[Rule<K, V>]) -> Vec<SpecWarning> {
+    let mut warnings = vec![];
+    for (index, rule) in rules.iter().enumerate() {
+        if !rule.pattern.starts_with('^') {
+            warnings.push(SpecWarning::MissingAnchor {
+                pattern: rule.pattern.clone(),
+            });
+        }
+        if rule.regex.is_match("") {
+            warnings.push(SpecWarning::MatchesEmpty {
+                pattern: rule.pattern.clone(),
+            });
+        }
+        if let Some(shadowed_by) = rules[..index].iter().position(|earlier| {
+            unanchored(&earlier.pattern) == unanchored(&rule.pattern)
+                || literal(&rule.pattern)
+                    .is_some_and(|text| match_token(&earlier.regex, &text) == Some(text.len()))
+        }) {
+            warnings.push(SpecWarning::Unreachable {
+                pattern: rule.pattern.clone(),
+                shadowed_by,
+            });
+        }
     }
-    fn match_token(&self, regex: &str, data_to_parse: &str) -> Option<String> {
-        if let Some(matched) = Regex::new(regex).unwrap().find(data_to_parse) {
-            return Some(matched.as_str().to_string());
+    warnings
+}
+
+// Only non-empty matches count, `compile` anchors them at the cursor.
+fn match_token(regex: &Regex, data_to_parse: &str) -> Option<usize> {
+    regex
+        .find(data_to_parse)
+        .filter(|matched| !matched.as_str().is_empty())
+        .map(|matched| matched.end())
+}
+
+fn unanchored(pattern: &str) -> &str {
+    pattern.strip_prefix('^').unwrap_or(pattern)
+}
+
+// The text matched by a pattern without any regex operator, e.g. `^\+\+`.
+fn literal(pattern: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut characters = unanchored(pattern).chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => match characters.next() {
+                Some(escaped) if !escaped.is_alphanumeric() => text.push(escaped),
+                _ => return None,
+            },
+            _ if "^$.|?*+()[]{}".contains(character) => return None,
+            _ => text.push(character),
         }
-        None
     }
-    fn parse(&mut self, value: &str) -> ParsedValue {
-        if let Ok(parsed) = value.parse::<u128>() {
-            return ParsedValue::Number(parsed);
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenizer(
+        rules: &[(&str, TokenType)],
+        to_parse: &str,
+        policy: MatchPolicy,
+    ) -> Tokenizer<TokenType, ParsedValue> {
+        let spec = rules
+            .iter()
+            .map(|(pattern, token_type)| (pattern.to_string(), token_type.clone()))
+            .collect();
+        Tokenizer::with_policy(TokenType::rules(spec).unwrap(), to_parse.to_owned(), policy)
+    }
+
+    fn next_type(tokenizer: &mut Tokenizer<TokenType, ParsedValue>) -> Option<TokenType> {
+        tokenizer
+            .get_next_token()
+            .unwrap()
+            .map(|token| token.token_type)
+    }
+
+    #[test]
+    fn test_longest_match_wins_over_spec_order() {
+        let mut tokenizer = tokenizer(
+            &[(r"^\d", TokenType::String), (r"^\d+", TokenType::Number)],
+            "42",
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(
+            tokenizer.get_next_token(),
+            Ok(Some(TokenValue {
+                token_type: TokenType::Number,
+                value: ParsedValue::Number(42),
+                span: Span::new(0, 2),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_spec_order_breaks_ties() {
+        let mut tokenizer = tokenizer(
+            &[
+                (r"^\d+", TokenType::Number),
+                (r"^[0-9]+", TokenType::String),
+            ],
+            "42",
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(next_type(&mut tokenizer), Some(TokenType::Number));
+    }
+
+    #[test]
+    fn test_first_match_policy() {
+        let mut tokenizer = tokenizer(
+            &[(r"^\d", TokenType::String), (r"^\d+", TokenType::Number)],
+            "42",
+            MatchPolicy::FirstMatch,
+        );
+        assert_eq!(next_type(&mut tokenizer), Some(TokenType::String));
+    }
+
+    #[test]
+    fn test_unanchored_rule_only_matches_at_cursor() {
+        let mut tokenizer = tokenizer(
+            &[
+                (r"^\d+", TokenType::Number),
+                (r"'[^']*'", TokenType::String),
+            ],
+            "42'abc'",
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(next_type(&mut tokenizer), Some(TokenType::Number));
+        assert_eq!(next_type(&mut tokenizer), Some(TokenType::String));
+    }
+
+    #[test]
+    fn test_unanchored_rule_stops_at_cursor() {
+        // Searching the rest of the input for a string at every token would
+        // take quadratic time.
+        let to_parse = "1; ".repeat(100_000);
+        let tokenizer = tokenizer(
+            &[
+                (r"\s+", TokenType::Null),
+                (r"'[^']*'", TokenType::String),
+                (r"\d+", TokenType::Number),
+                (r";", TokenType::SemiColon),
+            ],
+            &to_parse,
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(tokenizer.count(), 200_000);
+    }
+
+    #[test]
+    fn test_empty_matches_are_ignored() {
+        let mut tokenizer = tokenizer(
+            &[(r"^\s*", TokenType::Null), (r"^\d+", TokenType::Number)],
+            "42",
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(next_type(&mut tokenizer), Some(TokenType::Number));
+        assert_eq!(next_type(&mut tokenizer), None);
+    }
+
+    #[test]
+    fn test_spec_warnings() {
+        let tokenizer = tokenizer(
+            &[
+                (r"^\s*", TokenType::Null),
+                (r"\d+", TokenType::Number),
+                (r"^\d+", TokenType::String),
+            ],
+            "",
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(
+            tokenizer.warnings(),
+            &[
+                SpecWarning::MatchesEmpty {
+                    pattern: r"^\s*".to_owned()
+                },
+                SpecWarning::MissingAnchor {
+                    pattern: r"\d+".to_owned()
+                },
+                SpecWarning::Unreachable {
+                    pattern: r"^\d+".to_owned(),
+                    shadowed_by: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowed_literal() {
+        let warnings = |rules: &[(&str, TokenType)]| {
+            tokenizer(rules, "", MatchPolicy::LongestMatch)
+                .warnings()
+                .to_vec()
+        };
+        assert_eq!(
+            warnings(&[(r"^;+", TokenType::SemiColon), (r"^;", TokenType::String)]),
+            vec![SpecWarning::Unreachable {
+                pattern: r"^;".to_owned(),
+                shadowed_by: 0
+            }]
+        );
+        assert_eq!(
+            warnings(&[(r"^\d+", TokenType::Number), (r"^\.5", TokenType::String)]),
+            vec![]
+        );
+        // `^1` only takes the first character, so `^12` is the longest match.
+        assert_eq!(
+            warnings(&[(r"^1", TokenType::Number), (r"^12", TokenType::String)]),
+            vec![]
+        );
+        assert_eq!(
+            warnings(&[(r"^[0-9]+", TokenType::Number), (r"^12", TokenType::String)]),
+            vec![SpecWarning::Unreachable {
+                pattern: r"^12".to_owned(),
+                shadowed_by: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_number_out_of_range() {
+        let mut tokenizer = tokenizer(
+            &[(r"^\d+", TokenType::Number)],
+            &"9".repeat(40),
+            MatchPolicy::LongestMatch,
+        );
+        assert_eq!(
+            tokenizer.get_next_token(),
+            Err(DomainError::NumberTooLarge {
+                span: Span::new(0, 40)
+            })
+        );
+    }
+
+    #[test]
+    fn test_user_defined_token_types() {
+        #[derive(Debug, Clone, PartialEq)]
+        enum Calc {
+            Int,
+            Op,
+        }
+        let rules = vec![
+            Rule::skip(r"^\s+").unwrap(),
+            Rule::token(r"^\d+", Calc::Int, |value| Ok(value.parse::<i64>()?)).unwrap(),
+            Rule::token(r"^[-+*/]", Calc::Op, |value| {
+                Ok(-(value.as_bytes()[0] as i64))
+            })
+            .unwrap(),
+        ];
+        let mut tokenizer = Tokenizer::new(rules, "1 + 23".to_owned());
+        let mut tokens = vec![];
+        while let Some(token) = tokenizer.get_next_token().unwrap() {
+            tokens.push((token.token_type, token.value));
+        }
+        assert_eq!(
+            tokens,
+            vec![(Calc::Int, 1), (Calc::Op, -43), (Calc::Int, 23)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        assert!(matches!(
+            Rule::<TokenType, ParsedValue>::skip(r"^(\s+"),
+            Err(DomainError::InvalidRegex { .. })
+        ));
+    }
+
+    fn js_rules() -> Vec<Rule<TokenType, ParsedValue>> {
+        let spec = [
+            (r"^\s+", TokenType::Null),
+            (r"^;", TokenType::SemiColon),
+            (r"^\d+", TokenType::Number),
+            (r#"^"[^"]*"|^'[^']*'"#, TokenType::String),
+        ]
+        .into_iter()
+        .map(|(pattern, token_type)| (pattern.to_owned(), token_type))
+        .collect();
+        TokenType::rules(spec).unwrap()
+    }
+
+    // Hands out the input a few bytes at a time.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.0.len().min(self.1).min(buffer.len());
+            buffer[..read].copy_from_slice(&self.0[..read]);
+            self.0 = &self.0[read..];
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn test_reader_matches_string_input() {
+        let to_parse = "12345; 'héllo wörld';\n\"x\"; 7";
+        let expected: Vec<_> = Tokenizer::new(js_rules(), to_parse.to_owned()).collect();
+        for chunk_size in [1, 2, 3, 5, 64] {
+            let source: &'static [u8] = to_parse.as_bytes();
+            let streamed: Vec<_> = Tokenizer::from_reader(js_rules(), Trickle(source, 2))
+                .with_chunk_size(chunk_size)
+                .collect();
+            assert_eq!(streamed, expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_reader_memory_is_bounded() {
+        let to_parse = "42; 'abc'; ".repeat(10_000);
+        let source = std::io::Cursor::new(to_parse.into_bytes());
+        let mut tokenizer = Tokenizer::from_reader(js_rules(), source).with_chunk_size(64);
+        let mut count = 0;
+        let mut largest = 0;
+        while let Some(token) = tokenizer.next() {
+            let token = token.unwrap();
+            assert_eq!(token.span.start, (count / 4) * 11 + [0, 2, 4, 9][count % 4]);
+            count += 1;
+            largest = largest.max(tokenizer.buffer.len());
+        }
+        assert_eq!(count, 40_000);
+        assert!(largest <= 3 * 64, "buffer grew to {}", largest);
+    }
+
+    #[test]
+    fn test_unexpected_character_stops_iteration() {
+        let tokens: Vec<_> = Tokenizer::new(js_rules(), "1 @ 2".to_owned()).collect();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            tokens[1],
+            Err(DomainError::UnexpectedCharacter {
+                character: '@',
+                offset: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let source: &'static [u8] = b"1 \xff";
+        let tokens: Vec<_> = Tokenizer::from_reader(js_rules(), source).collect();
+        assert_eq!(
+            tokens.last(),
+            Some(&Err(DomainError::InvalidUtf8 { offset: 2 }))
+        );
+    }
+
+    #[test]
+    fn test_newline_before() {
+        let mut tokenizer = Tokenizer::new(js_rules(), "1 2\n3 \r\n 4".to_owned());
+        let mut found = vec![];
+        while tokenizer.get_next_token().unwrap().is_some() {
+            found.push(tokenizer.newline_before());
         }
-        ParsedValue::String(value.to_string())
+        assert_eq!(found, [false, false, true, true]);
     }
 }
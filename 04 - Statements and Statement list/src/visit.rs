@@ -0,0 +1,400 @@
+// Traversals over the tree built by `Parser`.
+//
+// Every `visit_*` method defaults to the matching `walk_*` function, which
+// calls `enter`, visits the children unless `enter` returned
+// `VisitControl::SkipChildren`, then calls `leave`. Override a `visit_*`
+// method to handle one kind of node, and call the `walk_*` function from it
+// to keep going into its children.
+
+use crate::models::{
+    Expression, ExpressionStatement, Literal, NumericLiteral, Program, Statement, StringLiteral,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VisitControl {
+    #[default]
+    Continue,
+    SkipChildren,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRef<'a> {
+    Program(&'a Program),
+    Statement(&'a Statement),
+    ExpressionStatement(&'a ExpressionStatement),
+    Expression(&'a Expression),
+    Literal(&'a Literal),
+    NumericLiteral(&'a NumericLiteral),
+    StringLiteral(&'a StringLiteral),
+}
+
+#[derive(Debug)]
+pub enum NodeMut<'a> {
+    Program(&'a mut Program),
+    Statement(&'a mut Statement),
+    ExpressionStatement(&'a mut ExpressionStatement),
+    Expression(&'a mut Expression),
+    Literal(&'a mut Literal),
+    NumericLiteral(&'a mut NumericLiteral),
+    StringLiteral(&'a mut StringLiteral),
+}
+
+pub trait Visit {
+    fn enter(&mut self, _node: NodeRef<'_>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn leave(&mut self, _node: NodeRef<'_>) {}
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) {
+        walk_expression_statement(self, statement)
+    }
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression)
+    }
+    fn visit_literal(&mut self, literal: &Literal) {
+        walk_literal(self, literal)
+    }
+    fn visit_numeric_literal(&mut self, literal: &NumericLiteral) {
+        walk_numeric_literal(self, literal)
+    }
+    fn visit_string_literal(&mut self, literal: &StringLiteral) {
+        walk_string_literal(self, literal)
+    }
+}
+
+pub fn walk_program<V: Visit + ?Sized>(visitor: &mut V, program: &Program) {
+    if visitor.enter(NodeRef::Program(program)) == VisitControl::Continue {
+        for statement in program.body.iter() {
+            visitor.visit_statement(statement);
+        }
+    }
+    visitor.leave(NodeRef::Program(program));
+}
+
+pub fn walk_statement<V: Visit + ?Sized>(visitor: &mut V, statement: &Statement) {
+    if visitor.enter(NodeRef::Statement(statement)) == VisitControl::Continue {
+        match statement {
+            Statement::Expression(statement) => visitor.visit_expression_statement(statement),
+        }
+    }
+    visitor.leave(NodeRef::Statement(statement));
+}
+
+pub fn walk_expression_statement<V: Visit + ?Sized>(
+    visitor: &mut V,
+    statement: &ExpressionStatement,
+) {
+    if visitor.enter(NodeRef::ExpressionStatement(statement)) == VisitControl::Continue {
+        visitor.visit_expression(&statement.expression);
+    }
+    visitor.leave(NodeRef::ExpressionStatement(statement));
+}
+
+pub fn walk_expression<V: Visit + ?Sized>(visitor: &mut V, expression: &Expression) {
+    if visitor.enter(NodeRef::Expression(expression)) == VisitControl::Continue {
+        match expression {
+            Expression::Literal(literal) => visitor.visit_literal(literal),
+        }
+    }
+    visitor.leave(NodeRef::Expression(expression));
+}
+
+pub fn walk_literal<V: Visit + ?Sized>(visitor: &mut V, literal: &Literal) {
+    if visitor.enter(NodeRef::Literal(literal)) == VisitControl::Continue {
+        match literal {
+            Literal::Numeric(literal) => visitor.visit_numeric_literal(literal),
+            Literal::String(literal) => visitor.visit_string_literal(literal),
+        }
+    }
+    visitor.leave(NodeRef::Literal(literal));
+}
+
+pub fn walk_numeric_literal<V: Visit + ?Sized>(visitor: &mut V, literal: &NumericLiteral) {
+    visitor.enter(NodeRef::NumericLiteral(literal));
+    visitor.leave(NodeRef::NumericLiteral(literal));
+}
+
+pub fn walk_string_literal<V: Visit + ?Sized>(visitor: &mut V, literal: &StringLiteral) {
+    visitor.enter(NodeRef::StringLiteral(literal));
+    visitor.leave(NodeRef::StringLiteral(literal));
+}
+
+pub trait VisitMut {
+    fn enter(&mut self, _node: NodeMut<'_>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn leave(&mut self, _node: NodeMut<'_>) {}
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement)
+    }
+    fn visit_expression_statement_mut(&mut self, statement: &mut ExpressionStatement) {
+        walk_expression_statement_mut(self, statement)
+    }
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression)
+    }
+    fn visit_literal_mut(&mut self, literal: &mut Literal) {
+        walk_literal_mut(self, literal)
+    }
+    fn visit_numeric_literal_mut(&mut self, literal: &mut NumericLiteral) {
+        walk_numeric_literal_mut(self, literal)
+    }
+    fn visit_string_literal_mut(&mut self, literal: &mut StringLiteral) {
+        walk_string_literal_mut(self, literal)
+    }
+}
+
+pub fn walk_program_mut<V: VisitMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    if visitor.enter(NodeMut::Program(program)) == VisitControl::Continue {
+        for statement in program.body.iter_mut() {
+            visitor.visit_statement_mut(statement);
+        }
+    }
+    visitor.leave(NodeMut::Program(program));
+}
+
+pub fn walk_statement_mut<V: VisitMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    if visitor.enter(NodeMut::Statement(statement)) == VisitControl::Continue {
+        match statement {
+            Statement::Expression(statement) => visitor.visit_expression_statement_mut(statement),
+        }
+    }
+    visitor.leave(NodeMut::Statement(statement));
+}
+
+pub fn walk_expression_statement_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    statement: &mut ExpressionStatement,
+) {
+    if visitor.enter(NodeMut::ExpressionStatement(statement)) == VisitControl::Continue {
+        visitor.visit_expression_mut(&mut statement.expression);
+    }
+    visitor.leave(NodeMut::ExpressionStatement(statement));
+}
+
+pub fn walk_expression_mut<V: VisitMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    if visitor.enter(NodeMut::Expression(expression)) == VisitControl::Continue {
+        match expression {
+            Expression::Literal(literal) => visitor.visit_literal_mut(literal),
+        }
+    }
+    visitor.leave(NodeMut::Expression(expression));
+}
+
+pub fn walk_literal_mut<V: VisitMut + ?Sized>(visitor: &mut V, literal: &mut Literal) {
+    if visitor.enter(NodeMut::Literal(literal)) == VisitControl::Continue {
+        match literal {
+            Literal::Numeric(literal) => visitor.visit_numeric_literal_mut(literal),
+            Literal::String(literal) => visitor.visit_string_literal_mut(literal),
+        }
+    }
+    visitor.leave(NodeMut::Literal(literal));
+}
+
+pub fn walk_numeric_literal_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    literal: &mut NumericLiteral,
+) {
+    visitor.enter(NodeMut::NumericLiteral(literal));
+    visitor.leave(NodeMut::NumericLiteral(literal));
+}
+
+pub fn walk_string_literal_mut<V: VisitMut + ?Sized>(visitor: &mut V, literal: &mut StringLiteral) {
+    visitor.enter(NodeMut::StringLiteral(literal));
+    visitor.leave(NodeMut::StringLiteral(literal));
+}
+
+// Owned transformation, each `fold_*` method returns the replacement of its
+// node. The `fold_*_children` functions rebuild a node from its folded
+// children.
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program_children(self, program)
+    }
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        fold_statement_children(self, statement)
+    }
+    fn fold_expression_statement(&mut self, statement: ExpressionStatement) -> ExpressionStatement {
+        fold_expression_statement_children(self, statement)
+    }
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression_children(self, expression)
+    }
+    fn fold_literal(&mut self, literal: Literal) -> Literal {
+        fold_literal_children(self, literal)
+    }
+    fn fold_numeric_literal(&mut self, literal: NumericLiteral) -> NumericLiteral {
+        literal
+    }
+    fn fold_string_literal(&mut self, literal: StringLiteral) -> StringLiteral {
+        literal
+    }
+}
+
+pub fn fold_program_children<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        body: program
+            .body
+            .into_iter()
+            .map(|statement| folder.fold_statement(statement))
+            .collect(),
+        ..program
+    }
+}
+
+pub fn fold_statement_children<F: Fold + ?Sized>(
+    folder: &mut F,
+    statement: Statement,
+) -> Statement {
+    match statement {
+        Statement::Expression(statement) => {
+            Statement::Expression(folder.fold_expression_statement(statement))
+        }
+    }
+}
+
+pub fn fold_expression_statement_children<F: Fold + ?Sized>(
+    folder: &mut F,
+    statement: ExpressionStatement,
+) -> ExpressionStatement {
+    ExpressionStatement {
+        expression: folder.fold_expression(statement.expression),
+        ..statement
+    }
+}
+
+pub fn fold_expression_children<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: Expression,
+) -> Expression {
+    match expression {
+        Expression::Literal(literal) => Expression::Literal(folder.fold_literal(literal)),
+    }
+}
+
+pub fn fold_literal_children<F: Fold + ?Sized>(folder: &mut F, literal: Literal) -> Literal {
+    match literal {
+        Literal::Numeric(literal) => Literal::Numeric(folder.fold_numeric_literal(literal)),
+        Literal::String(literal) => Literal::String(folder.fold_string_literal(literal)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::EqIgnoreSpan, parser_factory::ParserFactory};
+
+    fn parse(to_parse: &str) -> Program {
+        ParserFactory::create(to_parse.to_owned()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_enter_and_leave_order() {
+        #[derive(Default)]
+        struct Trace(Vec<String>);
+        impl Visit for Trace {
+            fn enter(&mut self, node: NodeRef<'_>) -> VisitControl {
+                self.0.push(format!("enter {}", name(node)));
+                VisitControl::Continue
+            }
+            fn leave(&mut self, node: NodeRef<'_>) {
+                self.0.push(format!("leave {}", name(node)));
+            }
+        }
+        fn name(node: NodeRef<'_>) -> &'static str {
+            match node {
+                NodeRef::Program(_) => "Program",
+                NodeRef::Statement(_) => "Statement",
+                NodeRef::ExpressionStatement(_) => "ExpressionStatement",
+                NodeRef::Expression(_) => "Expression",
+                NodeRef::Literal(_) => "Literal",
+                NodeRef::NumericLiteral(_) => "NumericLiteral",
+                NodeRef::StringLiteral(_) => "StringLiteral",
+            }
+        }
+        let mut trace = Trace::default();
+        trace.visit_program(&parse("1;"));
+        assert_eq!(
+            trace.0,
+            [
+                "enter Program",
+                "enter Statement",
+                "enter ExpressionStatement",
+                "enter Expression",
+                "enter Literal",
+                "enter NumericLiteral",
+                "leave NumericLiteral",
+                "leave Literal",
+                "leave Expression",
+                "leave ExpressionStatement",
+                "leave Statement",
+                "leave Program",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip_children() {
+        #[derive(Default)]
+        struct CountNumbers {
+            count: usize,
+        }
+        impl Visit for CountNumbers {
+            fn enter(&mut self, node: NodeRef<'_>) -> VisitControl {
+                match node {
+                    // Skip the statements after the first one.
+                    NodeRef::Statement(statement) if statement.span().start > 0 => {
+                        VisitControl::SkipChildren
+                    }
+                    _ => VisitControl::Continue,
+                }
+            }
+            fn visit_numeric_literal(&mut self, literal: &NumericLiteral) {
+                self.count += 1;
+                walk_numeric_literal(self, literal);
+            }
+        }
+        let mut visitor = CountNumbers::default();
+        visitor.visit_program(&parse("1; 2; 'a'; 3;"));
+        assert_eq!(visitor.count, 1);
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        struct Increment;
+        impl VisitMut for Increment {
+            fn visit_numeric_literal_mut(&mut self, literal: &mut NumericLiteral) {
+                literal.value += 1;
+            }
+        }
+        let mut program = parse("1; 'a'; 41;");
+        Increment.visit_program_mut(&mut program);
+        assert!(program.eq_ignore_span(&parse("2; 'a'; 42;")));
+    }
+
+    #[test]
+    fn test_fold() {
+        struct Stringify;
+        impl Fold for Stringify {
+            fn fold_literal(&mut self, literal: Literal) -> Literal {
+                match literal {
+                    Literal::Numeric(literal) => Literal::String(StringLiteral {
+                        value: literal.value.to_string(),
+                        span: literal.span,
+                    }),
+                    literal => literal,
+                }
+            }
+        }
+        let program = Stringify.fold_program(parse("1; 'a';"));
+        assert!(program.eq_ignore_span(&parse("'1'; 'a';")));
+    }
+}
@@ -0,0 +1,3 @@
+// leading comment
+1;
+/* block */ 'not a directive';
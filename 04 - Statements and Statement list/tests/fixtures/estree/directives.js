@@ -0,0 +1,2 @@
+"double";
+'single';
@@ -0,0 +1 @@
+18446744073709551616;